@@ -1,15 +1,22 @@
 use std::{
+  fmt::Display,
   hash::Hash,
   hint::black_box,
   time::{Duration, SystemTime},
 };
 
 use abstract_game::{test_games::ConnectN, Game, Solver};
-use cooperate::solvers::{
-  alpha_beta::AlphaBeta, iter_deep::IterativeDeepening, simple::SimpleSolver,
-  ttable_alpha_beta::TTAlphaBeta, ttable_solver::TTSolver,
+use cooperate::{
+  cooperate::{CooperativeSolver, Options},
+  move_order::{KillerMoves, TableScoreOrdering},
+  solvers::{
+    alpha_beta::AlphaBeta, iter_deep::IterativeDeepening, lazy_smp::LazySmp, simple::SimpleSolver,
+    ttable_alpha_beta::TTAlphaBeta, ttable_solver::TTSolver,
+  },
 };
 
+const NUM_THREADS: u32 = 8;
+
 fn time_solver<G: Game>(
   mut solver: impl Solver<Game = G>,
   initial_state: &G,
@@ -22,7 +29,11 @@ fn time_solver<G: Game>(
   SystemTime::now().duration_since(start).unwrap()
 }
 
-fn time_solvers<G: Game + Hash + Eq>(initial_state: &G, depth: u32) {
+fn time_solvers<G>(initial_state: &G, depth: u32)
+where
+  G: Game + Display + Send + Sync + Hash + Eq + 'static,
+  G::Move: Display + Send + PartialEq,
+{
   println!(
     "Simple time: {:?}",
     time_solver(SimpleSolver::new(), initial_state, depth)
@@ -39,10 +50,57 @@ fn time_solvers<G: Game + Hash + Eq>(initial_state: &G, depth: u32) {
     "TT+AB time: {:?}",
     time_solver(TTAlphaBeta::new(), initial_state, depth)
   );
+  println!(
+    "TT+AB ordered time: {:?}",
+    time_solver(
+      TTAlphaBeta::new().with_move_orderer((TableScoreOrdering, KillerMoves::new())),
+      initial_state,
+      depth
+    )
+  );
   println!(
     "Iter deep time: {:?}",
     time_solver(IterativeDeepening::new(), initial_state, depth)
   );
+  println!(
+    "Lazy SMP time: {:?}",
+    time_solver(LazySmp::new(NUM_THREADS), initial_state, depth)
+  );
+  println!(
+    "Cooperative time: {:?}",
+    time_solver(
+      CooperativeSolver::new(Options {
+        num_threads: NUM_THREADS,
+        ..Options::default()
+      }),
+      initial_state,
+      depth
+    )
+  );
+  println!(
+    "Cooperative transposition-driven time: {:?}",
+    time_solver(
+      CooperativeSolver::new(Options {
+        num_threads: NUM_THREADS,
+        transposition_driven: true,
+        ..Options::default()
+      }),
+      initial_state,
+      depth
+    )
+  );
+  println!(
+    "Cooperative alpha/beta time: {:?}",
+    time_solver(
+      CooperativeSolver::new(Options {
+        num_threads: NUM_THREADS,
+        alpha_beta: true,
+        ..Options::default()
+      }),
+      initial_state,
+      depth
+    )
+  );
 }
 
 fn main() {
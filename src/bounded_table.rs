@@ -0,0 +1,241 @@
+use std::{
+  hash::{BuildHasher, Hash},
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Mutex,
+  },
+};
+
+use abstract_game::Score;
+
+/// The number of entries in each bucket. A new state may replace any of the
+/// entries in the bucket it hashes to.
+const BUCKET_SIZE: usize = 4;
+
+/// Values which can be stored in a `BoundedTable`.
+pub trait TableValue: Copy {
+  /// Combines `self`, which is already in the table, with a new value for the
+  /// same state.
+  fn merge(self, new: Self) -> Self;
+
+  /// The depth this value was searched to. Entries with shallower values are
+  /// replaced first.
+  fn depth(&self) -> u32;
+}
+
+impl TableValue for Score {
+  fn merge(self, new: Self) -> Self {
+    Score::merge(&self, new)
+  }
+
+  fn depth(&self) -> u32 {
+    self.determined_depth()
+  }
+}
+
+struct Slot<G, V> {
+  game: G,
+  value: V,
+  /// The generation this entry was last written in.
+  generation: u32,
+}
+
+impl<G, V: TableValue> Slot<G, V> {
+  /// Entries with lower priority are replaced first. Entries from older
+  /// generations are the least valuable, followed by entries which have been
+  /// determined to shallower depths.
+  fn priority(&self, generation: u32) -> (bool, u32) {
+    (self.generation == generation, self.value.depth())
+  }
+}
+
+type Bucket<G, V> = Mutex<Vec<Slot<G, V>>>;
+
+/// A fixed-capacity concurrent transposition table. Each state hashes to a
+/// bucket of `BUCKET_SIZE` entries, and once a bucket is full, new states
+/// replace the entry with the lowest priority.
+pub struct BoundedTable<G, H, V = Score> {
+  buckets: Box<[Bucket<G, V>]>,
+  hasher: H,
+  generation: AtomicU32,
+}
+
+impl<G, H, V> BoundedTable<G, H, V>
+where
+  G: Hash + Eq,
+  H: BuildHasher,
+  V: TableValue,
+{
+  /// Constructs a table which holds `capacity` entries, rounded up to a
+  /// multiple of the bucket size.
+  pub fn with_capacity_and_hasher(capacity: usize, hasher: H) -> Self {
+    let num_buckets = capacity.div_ceil(BUCKET_SIZE).max(1);
+    Self {
+      buckets: (0..num_buckets).map(|_| Mutex::new(Vec::new())).collect(),
+      hasher,
+      generation: AtomicU32::new(0),
+    }
+  }
+
+  pub fn hasher(&self) -> &H {
+    &self.hasher
+  }
+
+  /// The number of entries the table can hold.
+  pub fn capacity(&self) -> usize {
+    self.buckets.len() * BUCKET_SIZE
+  }
+
+  fn bucket(&self, game: &G) -> &Bucket<G, V> {
+    let idx = self.hasher.hash_one(game) % self.buckets.len() as u64;
+    &self.buckets[idx as usize]
+  }
+
+  pub fn get(&self, game: &G) -> Option<V> {
+    self
+      .bucket(game)
+      .lock()
+      .unwrap()
+      .iter()
+      .find(|slot| &slot.game == game)
+      .map(|slot| slot.value)
+  }
+
+  /// Merges `value` into the entry for `game`, inserting it if it isn't in the
+  /// table. If the bucket is full, the entry with the lowest priority is
+  /// replaced.
+  pub fn update(&self, game: G, value: V) {
+    let generation = self.generation.load(Ordering::Relaxed);
+    let mut bucket = self.bucket(&game).lock().unwrap();
+
+    if let Some(slot) = bucket.iter_mut().find(|slot| slot.game == game) {
+      slot.value = slot.value.merge(value);
+      slot.generation = generation;
+      return;
+    }
+
+    let slot = Slot {
+      game,
+      value,
+      generation,
+    };
+    if bucket.len() < BUCKET_SIZE {
+      bucket.push(slot);
+    } else {
+      let victim = bucket
+        .iter_mut()
+        .min_by_key(|slot| slot.priority(generation))
+        .unwrap();
+      *victim = slot;
+    }
+  }
+
+  /// Starts a new generation. Entries which were last written before this are
+  /// replaced before any entries written after.
+  pub fn new_generation(&self) {
+    self.generation.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn len(&self) -> usize {
+    self
+      .buckets
+      .iter()
+      .map(|bucket| bucket.lock().unwrap().len())
+      .sum()
+  }
+
+  /// Calls `f` with each entry in the table, stopping at the first error.
+  pub fn try_for_each<E>(&self, mut f: impl FnMut(&G, V) -> Result<(), E>) -> Result<(), E> {
+    self.buckets.iter().try_for_each(|bucket| {
+      bucket
+        .lock()
+        .unwrap()
+        .iter()
+        .try_for_each(|slot| f(&slot.game, slot.value))
+    })
+  }
+
+  #[cfg(test)]
+  pub fn entries(&self) -> Vec<(G, V)>
+  where
+    G: Clone,
+  {
+    self
+      .buckets
+      .iter()
+      .flat_map(|bucket| {
+        bucket
+          .lock()
+          .unwrap()
+          .iter()
+          .map(|slot| (slot.game.clone(), slot.value))
+          .collect::<Vec<_>>()
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::hash_map::RandomState;
+
+  use abstract_game::Score;
+
+  use super::{BoundedTable, BUCKET_SIZE};
+
+  #[test]
+  fn test_capacity() {
+    const CAPACITY: usize = 64;
+    let table = BoundedTable::with_capacity_and_hasher(CAPACITY, RandomState::new());
+    for i in 0..1000u32 {
+      table.update(i, Score::tie(i % 10 + 1));
+    }
+
+    assert!(table.entries().len() <= CAPACITY);
+    for (key, score) in table.entries() {
+      assert_eq!(score, Score::tie(key % 10 + 1));
+    }
+  }
+
+  #[test]
+  fn test_merge() {
+    let table = BoundedTable::with_capacity_and_hasher(BUCKET_SIZE, RandomState::new());
+    table.update(0u32, Score::tie(3));
+    table.update(0u32, Score::win(5));
+    assert_eq!(table.get(&0), Some(Score::tie(3).merge(Score::win(5))));
+  }
+
+  #[test]
+  fn test_depth_preferred() {
+    // With a single bucket, every state competes for the same entries.
+    let table = BoundedTable::with_capacity_and_hasher(1, RandomState::new());
+    table.update(0u32, Score::guaranteed_tie());
+    for i in 1..=(2 * BUCKET_SIZE as u32) {
+      table.update(i, Score::tie(i));
+    }
+
+    assert_eq!(table.get(&0), Some(Score::guaranteed_tie()));
+    // The most recent state always makes it into the table.
+    assert!(table.get(&(2 * BUCKET_SIZE as u32)).is_some());
+    // The shallowest states are replaced first.
+    assert_eq!(table.get(&1), None);
+  }
+
+  #[test]
+  fn test_aging() {
+    let table = BoundedTable::with_capacity_and_hasher(1, RandomState::new());
+    table.update(0u32, Score::guaranteed_tie());
+    table.new_generation();
+    for i in 1..(BUCKET_SIZE as u32) {
+      table.update(i, Score::tie(i));
+    }
+
+    // The deep entry from the old generation is replaced before any of the
+    // shallow entries from the current generation.
+    table.update(BUCKET_SIZE as u32, Score::tie(1));
+    assert_eq!(table.get(&0), None);
+    for i in 1..=(BUCKET_SIZE as u32) {
+      assert!(table.get(&i).is_some());
+    }
+  }
+}
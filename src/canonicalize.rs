@@ -0,0 +1,81 @@
+use std::borrow::Cow;
+
+use abstract_game::{Game, Score};
+
+use crate::principal_variation::ScoreTable;
+
+/// Games with symmetries can implement this to have symmetric states share a
+/// single entry in transposition tables.
+pub trait Canonicalize {
+  /// Returns the representative of every state symmetric to this one. It must
+  /// be the same for all of them, and have the same score as this state.
+  fn canonicalize(&self) -> Self;
+}
+
+/// Maps game states to the keys they are stored under in tables. Tables and
+/// solvers only canonicalize states if they were constructed for a game which
+/// implements `Canonicalize`, since there is no way to tell otherwise.
+pub(crate) struct Canonicalizer<G> {
+  canonicalize: Option<fn(&G) -> G>,
+}
+
+impl<G> Canonicalizer<G> {
+  /// A canonicalizer which stores every state under itself.
+  pub fn identity() -> Self {
+    Self { canonicalize: None }
+  }
+
+  pub fn new() -> Self
+  where
+    G: Canonicalize,
+  {
+    Self {
+      canonicalize: Some(G::canonicalize),
+    }
+  }
+
+  /// Returns the key that `game` is stored under, borrowing `game` if it is
+  /// its own key.
+  pub fn key<'a>(&self, game: &'a G) -> Cow<'a, G>
+  where
+    G: Clone,
+  {
+    match self.canonicalize {
+      Some(canonicalize) => Cow::Owned(canonicalize(game)),
+      None => Cow::Borrowed(game),
+    }
+  }
+
+  /// Returns the key that `game` is stored under, consuming `game`.
+  pub fn owned_key(&self, game: G) -> G {
+    match self.canonicalize {
+      Some(canonicalize) => canonicalize(&game),
+      None => game,
+    }
+  }
+}
+
+impl<G> Clone for Canonicalizer<G> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<G> Copy for Canonicalizer<G> {}
+
+/// A view of a table keyed by `canonicalizer`, which looks up every state under
+/// its key.
+pub(crate) struct CanonicalTable<'a, T, G> {
+  pub table: &'a T,
+  pub canonicalizer: Canonicalizer<G>,
+}
+
+impl<T, G> ScoreTable<G> for CanonicalTable<'_, T, G>
+where
+  T: ScoreTable<G>,
+  G: Game + Clone,
+{
+  fn lookup_score(&self, game: &G) -> Option<Score> {
+    self.table.lookup_score(&self.canonicalizer.key(game))
+  }
+}
@@ -1,20 +1,38 @@
 use std::{
-  collections::{hash_map::RandomState, HashSet},
-  fmt::Display,
+  any::Any,
+  collections::hash_map::RandomState,
+  error::Error,
+  fmt::{self, Display, Formatter},
   hash::{BuildHasher, Hash},
-  sync::Arc,
+  io::{self, Read, Write},
+  mem,
+  panic::{self, AssertUnwindSafe},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Barrier, Mutex,
+  },
   thread,
+  time::{Duration, Instant},
 };
 
-use abstract_game::{Game, GameResult, Score, ScoreValue};
+use abstract_game::{Game, GameResult, Score, Solver};
 use rand::{rng, Rng};
 
 use crate::{
+  canonicalize::{Canonicalize, Canonicalizer},
+  database::Database,
+  evaluate::{Evaluate, Evaluation},
+  frontier::Frontier,
   global_data::GlobalData,
-  null_lock::NullLock,
+  immediate_win::{has_immediate_win, ImmediateWin},
+  metrics::Metrics,
+  move_order::MoveOrderer,
+  persist::{read_table, EncodeGame, PersistTable},
+  principal_variation::ScoreTable,
   search_worker::{start_worker, WorkerData},
   stack::Stack,
   table::Table,
+  zobrist::ZobristHash,
 };
 
 #[derive(Clone)]
@@ -25,66 +43,331 @@ pub struct Options {
   pub search_depth: u32,
   /// The depth to expand to for generating work units.
   pub unit_depth: u32,
+  /// If set, `unit_depth` is ignored, and work units are generated from the
+  /// shallowest depth with at least this many states per thread.
+  pub units_per_thread: Option<u32>,
+  /// If set, the size of each work unit is estimated from this many random
+  /// playouts, and the units are handed out largest first to whichever worker
+  /// has the least estimated work. Otherwise they are handed out at random.
+  pub balance_playouts: Option<u32>,
+  /// The longest the search may run for. Once exceeded, the search is abandoned
+  /// and returns what it has found so far.
+  pub time_limit: Option<Duration>,
+  /// The most game states the search may explore before it is abandoned.
+  pub node_limit: Option<u64>,
+  /// A handle which can be used to abandon the search from another thread.
+  pub cancel_handle: Option<CancelHandle>,
+  /// The most resolved states to keep in memory, or `None` to keep all of
+  /// them. Once full, the least valuable states are evicted, and may have to
+  /// be searched again if they are needed.
+  pub table_capacity: Option<usize>,
+  /// Whether to stop exploring the moves of a state once one of them proves
+  /// the state can't affect the score of the root, as in alpha-beta search.
+  /// This explores far fewer states, but the scores found are less determined
+  /// than those of a full search: a win may not be the fastest one, and a tie
+  /// may only be determined to a shallower depth. Later searches can reuse
+  /// less of them as a result.
+  pub alpha_beta: bool,
+  /// Whether to give every state an owning worker, chosen by the hash of the
+  /// state, and route each stack to the owner of its bottom state before
+  /// looking it up, rather than letting every worker look up any state. Each
+  /// state is then only looked up and claimed by its owner, at the cost of
+  /// handing stacks between workers. The tables are still shared by every
+  /// worker, since a state is committed by whichever worker finishes it.
+  pub transposition_driven: bool,
 }
 
-fn generate_frontier<G>(initial_state: G, options: &Options) -> Vec<*mut Stack<G>>
-where
-  G: Game + Hash + PartialEq + Eq + Display + 'static,
-  G::Move: Display,
-{
-  let mut visited_states = HashSet::new();
-  let mut frontier = vec![initial_state];
+impl Default for Options {
+  fn default() -> Self {
+    Self {
+      num_threads: thread::available_parallelism().map_or(1, |n| n.get() as u32),
+      search_depth: 0,
+      unit_depth: 0,
+      units_per_thread: None,
+      balance_playouts: None,
+      time_limit: None,
+      node_limit: None,
+      cancel_handle: None,
+      table_capacity: None,
+      alpha_beta: false,
+      transposition_driven: false,
+    }
+  }
+}
+
+impl Options {
+  /// Returns a builder for options, starting from the defaults.
+  pub fn builder() -> OptionsBuilder {
+    OptionsBuilder::default()
+  }
 
-  for _ in 0..options.unit_depth {
-    let mut next_frontier = Vec::new();
+  /// Checks that these options can be searched with. A `search_depth` of 0 is
+  /// allowed, for options whose depth is chosen separately for each search, as
+  /// by `CooperativeSolver`.
+  pub fn validate(&self) -> Result<(), SolveError> {
+    if self.num_threads == 0 {
+      return Err(SolveError::InvalidOptions("num_threads must be at least 1"));
+    }
+    if self.units_per_thread == Some(0) {
+      return Err(SolveError::InvalidOptions(
+        "units_per_thread must be at least 1",
+      ));
+    }
+    if self.balance_playouts == Some(0) {
+      return Err(SolveError::InvalidOptions(
+        "balance_playouts must be at least 1",
+      ));
+    }
+    // `unit_depth` is ignored when the frontier is sized by `units_per_thread`.
+    if self.units_per_thread.is_none()
+      && self.search_depth != 0
+      && self.unit_depth >= self.search_depth
+    {
+      return Err(SolveError::InvalidOptions(
+        "unit_depth must be less than search_depth",
+      ));
+    }
+    Ok(())
+  }
 
-    for state in frontier.into_iter() {
-      for m in state.each_move() {
-        let child = state.with_move(m);
-        if visited_states.insert(child.clone()) {
-          next_frontier.push(child);
-        }
+  /// Checks that these options can be searched with as they are, including
+  /// their `search_depth`.
+  fn validate_search(&self) -> Result<(), SolveError> {
+    if self.search_depth == 0 {
+      return Err(SolveError::InvalidOptions(
+        "search_depth must be at least 1",
+      ));
+    }
+    self.validate()
+  }
+}
+
+/// Builds `Options`, checking that they are valid.
+#[derive(Clone, Default)]
+pub struct OptionsBuilder {
+  options: Options,
+}
+
+impl OptionsBuilder {
+  pub fn num_threads(mut self, num_threads: u32) -> Self {
+    self.options.num_threads = num_threads;
+    self
+  }
+
+  pub fn search_depth(mut self, search_depth: u32) -> Self {
+    self.options.search_depth = search_depth;
+    self
+  }
+
+  pub fn unit_depth(mut self, unit_depth: u32) -> Self {
+    self.options.unit_depth = unit_depth;
+    self
+  }
+
+  pub fn units_per_thread(mut self, units_per_thread: u32) -> Self {
+    self.options.units_per_thread = Some(units_per_thread);
+    self
+  }
+
+  pub fn balance_playouts(mut self, balance_playouts: u32) -> Self {
+    self.options.balance_playouts = Some(balance_playouts);
+    self
+  }
+
+  pub fn time_limit(mut self, time_limit: Duration) -> Self {
+    self.options.time_limit = Some(time_limit);
+    self
+  }
+
+  pub fn node_limit(mut self, node_limit: u64) -> Self {
+    self.options.node_limit = Some(node_limit);
+    self
+  }
+
+  pub fn cancel_handle(mut self, cancel_handle: CancelHandle) -> Self {
+    self.options.cancel_handle = Some(cancel_handle);
+    self
+  }
+
+  pub fn table_capacity(mut self, table_capacity: usize) -> Self {
+    self.options.table_capacity = Some(table_capacity);
+    self
+  }
+
+  pub fn alpha_beta(mut self, alpha_beta: bool) -> Self {
+    self.options.alpha_beta = alpha_beta;
+    self
+  }
+
+  pub fn transposition_driven(mut self, transposition_driven: bool) -> Self {
+    self.options.transposition_driven = transposition_driven;
+    self
+  }
+
+  /// Returns the options, or the reason they can't be searched with.
+  pub fn build(self) -> Result<Options, SolveError> {
+    self.options.validate()?;
+    Ok(self.options)
+  }
+}
+
+/// The reasons a search can fail to produce a result.
+#[derive(Debug)]
+pub enum SolveError {
+  /// The options can't be searched with, for the given reason.
+  InvalidOptions(&'static str),
+  /// The worker with index `thread_idx` panicked with `payload`. The stacks it
+  /// held were lost, so the search was abandoned.
+  WorkerPanicked {
+    thread_idx: u32,
+    payload: Box<dyn Any + Send>,
+  },
+  /// Every stack was freed without the root state being resolved, e.g.
+  /// because it was evicted from a bounded table.
+  UnresolvedRoot,
+}
+
+impl Display for SolveError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidOptions(reason) => write!(f, "invalid options: {reason}"),
+      Self::WorkerPanicked {
+        thread_idx,
+        payload,
+      } => {
+        let message = payload
+          .downcast_ref::<&str>()
+          .copied()
+          .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+          .unwrap_or("(non-string payload)");
+        write!(f, "worker {thread_idx} panicked: {message}")
       }
+      Self::UnresolvedRoot => write!(f, "the search finished without resolving the root"),
     }
+  }
+}
 
-    frontier = next_frontier;
+impl Error for SolveError {}
+
+/// A handle for cancelling searches from another thread. Cancellation is
+/// permanent, so a cancelled handle will stop any search it is passed to.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle {
+  cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+  pub fn new() -> Self {
+    Self::default()
   }
 
-  frontier
-    .into_iter()
-    .map(|state| {
-      Box::into_raw(Box::new(Stack::make_root(
-        state,
-        options.search_depth - options.unit_depth,
-      )))
-    })
-    .collect()
+  /// Stops every search using this handle as soon as possible.
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Release);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Acquire)
+  }
 }
 
+/// The result of solving a game state.
+pub struct SolveResult<G>
+where
+  G: Game,
+{
+  /// The score of the root game state.
+  pub score: Score,
+  /// The best move to make from the root game state, or `None` if there are no
+  /// legal moves. When searching with evaluations, this is the move with the
+  /// best evaluation.
+  pub best_move: Option<G::Move>,
+  /// The sequence of best moves for both players starting with `best_move`, as
+  /// far as the resolved states determine them. This may end before the game
+  /// does if the line runs into states which were never resolved, such as
+  /// states past the search depth.
+  pub principal_variation: Vec<G::Move>,
+  /// The evaluation of the root game state, if the search was tracking
+  /// evaluations and got far enough to evaluate it.
+  pub evaluation: Option<Evaluation>,
+  /// False if the search was cancelled or ran out of budget before it
+  /// finished, in which case the rest of the result only reflects what had
+  /// been resolved by then (possibly nothing).
+  pub complete: bool,
+  /// The sum of the metrics from every worker.
+  pub metrics: Metrics,
+  /// The metrics from each worker, indexed by thread index.
+  pub worker_metrics: Vec<Metrics>,
+}
+
+/// Makes a root stack for searching `game` to `depth`.
+fn make_root<G>(game: G, depth: u32, options: &Options) -> Stack<G>
+where
+  G: Game + Display + 'static,
+  G::Move: Display,
+{
+  let stack = Stack::make_root(game, depth);
+  if options.alpha_beta {
+    stack.with_alpha_beta()
+  } else {
+    stack
+  }
+}
+
+#[cfg(test)]
 fn construct_globals<G, H>(game: &G, options: Options, hasher: H) -> Arc<GlobalData<G, H>>
 where
   G: Game + Display + Hash + PartialEq + Eq + 'static,
   G::Move: Display,
   H: BuildHasher + Clone,
 {
-  let globals = Arc::new(GlobalData::with_hasher(
+  let mut globals = GlobalData::with_table(
     options.search_depth,
     options.num_threads,
-    hasher,
-  ));
+    make_table(&options, hasher),
+  );
+  globals.set_transposition_driven(options.transposition_driven);
+  queue_frontier(game, &options, &globals);
+  Arc::new(globals)
+}
 
-  let mut rng = rng();
-  for stack in generate_frontier(game.clone(), &options).into_iter() {
-    let rand_idx = rng.random_range(0..options.num_threads);
-    globals
-      .queue(rand_idx)
-      .push(unsafe { NullLock::new(stack) });
+/// Splits the search of `game` into a tree of stacks `unit_depth` moves deep,
+/// or as deep as `units_per_thread` asks for, distributing the stacks at its
+/// frontier across the worker queues. Returns the metrics from building the
+/// frontier.
+fn queue_frontier<G, H>(game: &G, options: &Options, globals: &GlobalData<G, H>) -> Metrics
+where
+  G: Game + Display + Hash + PartialEq + Eq + 'static,
+  G::Move: Display,
+  H: BuildHasher + Clone,
+{
+  let stack_ptr = Box::into_raw(Box::new(make_root(
+    game.clone(),
+    options.search_depth,
+    options,
+  )));
+  globals.new_live_stack();
+  let mut frontier = match options.units_per_thread {
+    Some(units_per_thread) => Frontier::with_units(
+      globals,
+      game,
+      units_per_thread as usize * options.num_threads as usize,
+      // Frontier stacks need at least one move left to search.
+      options.search_depth - 1,
+    ),
+    None => Frontier::new(globals, game, options.unit_depth),
+  };
+  frontier.split(stack_ptr, 0);
+  match options.balance_playouts {
+    Some(playouts) => frontier.queue_balanced(options.num_threads, playouts),
+    None => {
+      let mut rng = rng();
+      frontier.queue(&mut || rng.random_range(0..options.num_threads))
+    }
   }
-
-  globals
 }
 
-pub fn solve<G>(game: &G, options: Options) -> Score
+pub fn solve<G>(game: &G, options: Options) -> Result<SolveResult<G>, SolveError>
 where
   G: Game + Display + Send + Sync + Hash + PartialEq + Eq + 'static,
   G::Move: Display,
@@ -92,85 +375,599 @@ where
   solve_with_hasher(game, options, RandomState::new())
 }
 
-fn playout<G, H>(game: &G, tbl: &Table<G, H>, depth: u32)
+/// Releases the workers of a pool from their start barrier for the last time,
+/// telling them to exit. This runs on drop so that the workers also exit if
+/// the caller panics, since otherwise the thread scope would wait on them
+/// forever.
+struct StopWorkers<'a> {
+  start: &'a Barrier,
+  stopping: &'a AtomicBool,
+}
+
+impl Drop for StopWorkers<'_> {
+  fn drop(&mut self) {
+    self.stopping.store(true, Ordering::Relaxed);
+    self.start.wait();
+  }
+}
+
+/// Spawns `num_threads` workers on `globals`, then calls `f` with a function
+/// which runs the workers until every stack in the search has been freed and
+/// returns each worker's metrics. The same threads are reused each time it is
+/// called, so the caller can queue several searches in a row without
+/// respawning the thread pool.
+///
+/// If a worker panics, the search is aborted and the function returns the
+/// panic instead. The globals can't be searched with after that.
+fn with_worker_pool<G, H, R>(
+  globals: &Arc<GlobalData<G, H>>,
+  num_threads: u32,
+  f: impl FnOnce(&dyn Fn() -> Result<Vec<Metrics>, SolveError>) -> R,
+) -> R
 where
   G: Game + Display + Send + Sync + Hash + PartialEq + Eq + 'static,
   G::Move: Display,
   H: BuildHasher + Clone + Send + Sync + 'static,
 {
-  println!("{}", game);
-  if depth == 0 || game.finished() != GameResult::NotFinished {
-    return;
+  // Workers wait on `start` until the caller has queued work, and on `done`
+  // once all of the work has been finished.
+  let start = Barrier::new(num_threads as usize + 1);
+  let done = Barrier::new(num_threads as usize + 1);
+  let stopping = AtomicBool::new(false);
+  // The metrics from each worker's last run, or what it panicked with.
+  let worker_results: Vec<Mutex<thread::Result<Metrics>>> = (0..num_threads)
+    .map(|_| Mutex::new(Ok(Metrics::new())))
+    .collect();
+
+  thread::scope(|scope| {
+    let thread_handles: Vec<_> = (0..num_threads)
+      .map(|thread_idx| {
+        let (start, done, stopping) = (&start, &done, &stopping);
+        let worker_result = &worker_results[thread_idx as usize];
+        thread::Builder::new()
+          .name(format!("worker_{thread_idx}"))
+          .spawn_scoped(scope, move || loop {
+            start.wait();
+            if stopping.load(Ordering::Relaxed) {
+              break;
+            }
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+              start_worker(WorkerData::new(thread_idx, globals.clone()))
+            }));
+            if result.is_err() {
+              // The other workers can't finish the search without the stacks
+              // this one held.
+              globals.abort();
+            }
+            *worker_result.lock().unwrap() = result;
+            done.wait();
+          })
+          .unwrap()
+      })
+      .collect();
+
+    let stop_workers = StopWorkers {
+      start: &start,
+      stopping: &stopping,
+    };
+    let result = f(&|| {
+      start.wait();
+      done.wait();
+      let worker_metrics = worker_results
+        .iter()
+        .enumerate()
+        .map(|(thread_idx, result)| {
+          mem::replace(&mut *result.lock().unwrap(), Ok(Metrics::new())).map_err(|payload| {
+            SolveError::WorkerPanicked {
+              thread_idx: thread_idx as u32,
+              payload,
+            }
+          })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+      debug_assert_eq!(globals.stack_counts().total(), 0);
+      Ok(worker_metrics)
+    });
+    drop(stop_workers);
+
+    for thread in thread_handles.into_iter() {
+      thread
+        .join()
+        .expect("Workers should catch their own panics.");
+    }
+    result
+  })
+}
+
+pub fn solve_with_hasher<G, H>(
+  game: &G,
+  options: Options,
+  hasher: H,
+) -> Result<SolveResult<G>, SolveError>
+where
+  G: Game + Display + Send + Sync + Hash + PartialEq + Eq + 'static,
+  G::Move: Display,
+  H: BuildHasher + Clone + Send + Sync + 'static,
+{
+  options.validate_search()?;
+  let mut globals = GlobalData::with_table(
+    options.search_depth,
+    options.num_threads,
+    make_table(&options, hasher),
+  );
+  set_budget(&mut globals, &options);
+  globals.set_transposition_driven(options.transposition_driven);
+  let globals = Arc::new(globals);
+  with_worker_pool(&globals, options.num_threads, |run_workers| {
+    search(game, &options, &globals, run_workers)
+  })
+}
+
+/// Constructs the resolved states table described by `options`.
+fn make_table<G, H>(options: &Options, hasher: H) -> Table<G, H>
+where
+  G: Game + Hash + Eq,
+  H: BuildHasher + Clone,
+{
+  match options.table_capacity {
+    Some(capacity) => Table::bounded(capacity, hasher),
+    None => Table::with_hasher(hasher),
+  }
+}
+
+/// Limits the searches on `globals` to the budget in `options`, starting now.
+fn set_budget<G, H>(globals: &mut GlobalData<G, H>, options: &Options)
+where
+  G: Game + Display + Hash + PartialEq + Eq + 'static,
+  G::Move: Display,
+  H: BuildHasher + Clone,
+{
+  globals.set_budget(
+    options
+      .time_limit
+      .map(|time_limit| Instant::now() + time_limit),
+    options.node_limit,
+    options.cancel_handle.clone().unwrap_or_default(),
+  );
+}
+
+/// Searches `game` on `globals`, which must not have any outstanding stacks,
+/// using `run_workers` to run the worker pool.
+fn search<G, H>(
+  game: &G,
+  options: &Options,
+  globals: &GlobalData<G, H>,
+  run_workers: &dyn Fn() -> Result<Vec<Metrics>, SolveError>,
+) -> Result<SolveResult<G>, SolveError>
+where
+  G: Game + Display + Send + Sync + Hash + PartialEq + Eq + 'static,
+  G::Move: Display,
+  H: BuildHasher + Clone + Send + Sync + 'static,
+{
+  let frontier_metrics = queue_frontier(game, options, globals);
+  let mut worker_metrics = run_workers()?;
+  // The frontier is split on this thread, which is counted as the first worker.
+  worker_metrics[0] += frontier_metrics;
+  let metrics = worker_metrics.iter().cloned().sum();
+
+  let table = globals.resolved_states_table();
+  // Scores only rank moves by what they prove, so when there are evaluations,
+  // they choose the moves instead.
+  let principal_variation = if globals.evaluating() {
+    globals.evaluated_variation(game, options.search_depth)
+  } else {
+    table
+      .principal_variation(game, options.search_depth)
+      .moves()
+      .collect::<Vec<_>>()
+  };
+  let evaluation = globals.committed_evaluation(game);
+  if globals.stopped() {
+    return Ok(SolveResult {
+      score: table.get(game).unwrap_or(Score::NO_INFO),
+      best_move: principal_variation.first().copied().or_else(|| {
+        if globals.evaluating() {
+          globals.best_evaluated_move(game)
+        } else {
+          table.best_known_move(game, options.search_depth)
+        }
+      }),
+      principal_variation,
+      evaluation,
+      complete: false,
+      metrics,
+      worker_metrics,
+    });
+  }
+
+  let score = table.get(game).ok_or(SolveError::UnresolvedRoot)?;
+
+  Ok(SolveResult {
+    score,
+    best_move: principal_variation.first().copied(),
+    principal_variation,
+    evaluation,
+    complete: true,
+    metrics,
+    worker_metrics,
+  })
+}
+
+/// Adapts the cooperative search to the `Solver` interface, so it can be used
+/// anywhere the serial solvers are.
+pub struct CooperativeSolver<G, H>
+where
+  G: Game,
+{
+  options: Options,
+  hasher: H,
+  /// The globals from the most recent search. The resolved states are kept
+  /// between searches, so later searches can reuse everything proven by
+  /// earlier ones.
+  globals: Option<Arc<GlobalData<G, H>>>,
+  /// The deepest search, or loaded table, that the resolved states come from.
+  table_depth: u32,
+  /// A database of precomputed scores which every search consults.
+  database: Option<Arc<Database<G>>>,
+  /// Orders the moves of every state the workers search.
+  move_orderer: Option<Arc<dyn MoveOrderer<G> + Send + Sync>>,
+  /// If set, states with an immediate win are resolved without exploring them.
+  immediate_win_check: Option<fn(&G) -> bool>,
+  /// If set, states at the depth horizon are evaluated with this heuristic.
+  evaluate: Option<fn(&G) -> i32>,
+  canonicalizer: Canonicalizer<G>,
+  /// If set, the table stores the Zobrist hash of each state in place of the
+  /// state.
+  zobrist_hash: Option<fn(&G) -> u64>,
+}
+
+impl<G> CooperativeSolver<G, RandomState>
+where
+  G: Game + Display + Send + Sync + Hash + PartialEq + Eq + 'static,
+  G::Move: Display,
+{
+  /// Constructs a solver which searches with `options`. `options.search_depth`
+  /// is ignored in favor of the depth passed to `best_move`, and the budget in
+  /// `options` applies to each call separately.
+  pub fn new(options: Options) -> Self {
+    Self::with_hasher(options, RandomState::new())
+  }
+}
+
+impl<G, H> CooperativeSolver<G, H>
+where
+  G: Game + Display + Send + Sync + Hash + PartialEq + Eq + 'static,
+  G::Move: Display,
+  H: BuildHasher + Clone + Send + Sync + 'static,
+{
+  pub fn with_hasher(options: Options, hasher: H) -> Self {
+    Self {
+      options,
+      hasher,
+      globals: None,
+      table_depth: 0,
+      database: None,
+      move_orderer: None,
+      immediate_win_check: None,
+      evaluate: None,
+      canonicalizer: Canonicalizer::identity(),
+      zobrist_hash: None,
+    }
+  }
+
+  /// Makes the solver store symmetric states under a single entry of its table.
+  /// This discards every state resolved so far.
+  pub fn with_canonicalization(self) -> Self
+  where
+    G: Canonicalize,
+  {
+    Self {
+      globals: None,
+      table_depth: 0,
+      canonicalizer: Canonicalizer::new(),
+      ..self
+    }
+  }
+
+  pub fn options(&self) -> &Options {
+    &self.options
+  }
+
+  /// Returns the score of `game` found by the most recent search, if it was
+  /// resolved.
+  pub fn cached_score(&self, game: &G) -> Option<Score> {
+    self
+      .globals
+      .as_ref()
+      .and_then(|globals| globals.resolved_states_table().get(game))
+  }
+
+  /// Returns the table of states resolved by every search so far, if there
+  /// has been one.
+  pub fn table(&self) -> Option<&Table<G, H>> {
+    self
+      .globals
+      .as_ref()
+      .map(|globals| globals.resolved_states_table())
+  }
+
+  /// Returns the options to search `game` to `depth` with.
+  fn options_for_depth(&self, depth: u32) -> Options {
+    Options {
+      search_depth: depth,
+      // The frontier can't be expanded past the depth of the search, and
+      // frontier stacks need at least one move left to search.
+      unit_depth: self.options.unit_depth.min(depth.saturating_sub(1)),
+      ..self.options.clone()
+    }
+  }
+
+  /// Constructs an empty table of resolved states for this solver.
+  fn new_table(&self) -> Table<G, H> {
+    let table = match self.zobrist_hash {
+      Some(zobrist_hash) => Table::zobrist(
+        self.options.table_capacity,
+        self.hasher.clone(),
+        zobrist_hash,
+      ),
+      None => make_table(&self.options, self.hasher.clone()),
+    };
+    table.with_canonicalizer(self.canonicalizer)
+  }
+
+  /// Returns the globals for a search to at most `depth`, keeping the states
+  /// resolved by previous searches. The budget from this solver's options
+  /// starts now.
+  fn take_globals(&mut self, depth: u32) -> Arc<GlobalData<G, H>> {
+    let mut globals = match self.globals.take() {
+      Some(mut globals) => {
+        Arc::get_mut(&mut globals)
+          .expect("Every worker should have released the globals after the last search.")
+          .reset(depth);
+        globals
+      }
+      None => Arc::new(GlobalData::with_table(
+        depth,
+        self.options.num_threads,
+        self.new_table(),
+      )),
+    };
+    let globals_mut = Arc::get_mut(&mut globals).unwrap();
+    set_budget(globals_mut, &self.options);
+    globals_mut.set_database(self.database.clone());
+    globals_mut.set_move_orderer(self.move_orderer.clone());
+    globals_mut.set_immediate_win_check(self.immediate_win_check);
+    globals_mut.set_evaluate(self.evaluate);
+    globals_mut.set_transposition_driven(self.options.transposition_driven);
+    globals
+  }
+
+  /// Returns the result of searching `game` to `depth` with this solver's
+  /// options. If a worker panics, every state resolved so far is discarded.
+  pub fn solve(&mut self, game: &G, depth: u32) -> Result<SolveResult<G>, SolveError> {
+    let options = self.options_for_depth(depth);
+    options.validate_search()?;
+    let globals = self.take_globals(depth);
+    let result = with_worker_pool(&globals, options.num_threads, |run_workers| {
+      search(game, &options, &globals, run_workers)
+    });
+    self.keep_globals(globals, depth);
+    result
+  }
+
+  /// Keeps the globals from a search to `depth` for later searches, unless the
+  /// search was aborted, in which case they can't be searched with again.
+  fn keep_globals(&mut self, globals: Arc<GlobalData<G, H>>, depth: u32) {
+    if globals.aborted() {
+      self.clear();
+      return;
+    }
+    self.globals = Some(globals);
+    self.table_depth = self.table_depth.max(depth);
   }
 
-  for mv in game.each_move() {
-    let next_state = game.with_move(mv);
-    if depth == 1 && next_state.finished() != GameResult::NotFinished {
-      playout(&next_state, tbl, depth - 1);
+  /// Searches `game` to depth 1, 2, ... up to `max_depth` on the same thread
+  /// pool, calling `on_iteration` with the depth and result of each completed
+  /// iteration. Each iteration reuses the states resolved by the previous ones,
+  /// and the search stops early once the score of `game` is fully determined.
+  /// The budget in this solver's options applies to all iterations together,
+  /// and an iteration which runs out of budget is not reported. Returns the
+  /// result of the last iteration.
+  pub fn solve_iterative(
+    &mut self,
+    game: &G,
+    max_depth: u32,
+    mut on_iteration: impl FnMut(u32, &SolveResult<G>),
+  ) -> Result<SolveResult<G>, SolveError> {
+    if max_depth == 0 {
+      return Err(SolveError::InvalidOptions("max_depth must be at least 1"));
     }
-    if depth != 1 {
-      if let Some(score) = tbl.get(&next_state) {
-        if score.determined(depth) && score.score_at_depth(depth) != ScoreValue::Tie {
-          println!("{score}");
-          playout(&next_state, tbl, depth - 1);
+    self.options.validate()?;
+    let globals = self.take_globals(max_depth);
+    let result = with_worker_pool(&globals, self.options.num_threads, |run_workers| {
+      let mut result = SolveResult {
+        score: Score::NO_INFO,
+        best_move: None,
+        principal_variation: Vec::new(),
+        evaluation: None,
+        complete: true,
+        metrics: Metrics::new(),
+        worker_metrics: Vec::new(),
+      };
+      for depth in 1..=max_depth {
+        result = search(game, &self.options_for_depth(depth), &globals, run_workers)?;
+        if !result.complete {
+          break;
+        }
+        on_iteration(depth, &result);
+        if result.score.fully_determined() {
+          break;
         }
       }
+      Ok(result)
+    });
+    self.keep_globals(globals, max_depth);
+    result
+  }
+
+  /// Makes the solver store only the Zobrist hash of each resolved state, which
+  /// takes much less memory for games with large states. States with the same
+  /// hash share an entry, and tables of hashes can't be saved with
+  /// `save_table`. This discards every state resolved so far.
+  pub fn with_zobrist_keys(self) -> Self
+  where
+    G: ZobristHash,
+  {
+    Self {
+      globals: None,
+      table_depth: 0,
+      zobrist_hash: Some(G::zobrist_hash),
+      ..self
+    }
+  }
+
+  /// Makes the workers explore the moves of each state in the order chosen by
+  /// `move_orderer`. This only pays off with `Options::alpha_beta`, since
+  /// otherwise every move is explored anyway.
+  pub fn with_move_orderer(
+    self,
+    move_orderer: impl MoveOrderer<G> + Send + Sync + 'static,
+  ) -> Self {
+    Self {
+      move_orderer: Some(Arc::new(move_orderer)),
+      ..self
+    }
+  }
+
+  /// Makes the workers check every state for an immediate win before exploring
+  /// it, resolving states with one to a win in one move.
+  pub fn with_immediate_wins(self) -> Self
+  where
+    G: ImmediateWin,
+  {
+    Self {
+      immediate_win_check: Some(has_immediate_win::<G>),
+      ..self
+    }
+  }
+
+  /// Makes the workers evaluate the states at the depth horizon with the
+  /// game's `Evaluate` heuristic, and propagate the evaluations up to the root
+  /// alongside the scores. Results then carry the evaluation of the root, and
+  /// pick the move with the best evaluation, so the solver can play games too
+  /// large to solve. With `Options::alpha_beta`, moves are still cut off by
+  /// scores alone, so evaluations may come from only some of the children.
+  pub fn with_evaluation(self) -> Self
+  where
+    G: Evaluate,
+  {
+    Self {
+      evaluate: Some(G::evaluate),
+      ..self
+    }
+  }
+
+  /// Makes every search from now on look up states in `database` before
+  /// searching them.
+  pub fn set_database(&mut self, database: Option<Arc<Database<G>>>) {
+    self.database = database;
+  }
+
+  /// Writes every state resolved so far to `writer`, in the format read by
+  /// `load_table`.
+  pub fn save_table<W: Write>(&self, writer: W) -> io::Result<()>
+  where
+    G: EncodeGame,
+  {
+    match &self.globals {
+      Some(globals) => globals
+        .resolved_states_table()
+        .save(writer, self.table_depth),
+      None => self.new_table().save(writer, 0),
     }
   }
+
+  /// Adds the states from a table written by `save_table` to the resolved
+  /// states, so later searches can use them. Returns the search depth the table
+  /// was saved with.
+  pub fn load_table<R: Read>(&mut self, reader: R) -> io::Result<u32>
+  where
+    G: EncodeGame,
+  {
+    let depth = match &self.globals {
+      Some(globals) => {
+        let table = globals.resolved_states_table();
+        read_table(reader, |game, score| table.update(game, score))?
+      }
+      None => {
+        let mut table = self.new_table();
+        let depth = table.load(reader)?;
+        self.globals = Some(Arc::new(GlobalData::with_table(
+          depth,
+          self.options.num_threads,
+          table,
+        )));
+        depth
+      }
+    };
+    self.table_depth = self.table_depth.max(depth);
+    Ok(depth)
+  }
+
+  /// Discards every state resolved by previous searches.
+  pub fn clear(&mut self) {
+    self.globals = None;
+    self.table_depth = 0;
+  }
 }
 
-pub fn solve_with_hasher<G, H>(game: &G, options: Options, hasher: H) -> Score
+impl<G, H> Solver for CooperativeSolver<G, H>
 where
   G: Game + Display + Send + Sync + Hash + PartialEq + Eq + 'static,
   G::Move: Display,
   H: BuildHasher + Clone + Send + Sync + 'static,
 {
-  let globals = construct_globals(game, options.clone(), hasher);
-  let thread_handles: Vec<_> = (0..options.num_threads)
-    .map(|thread_idx| {
-      let globals = globals.clone();
-      thread::Builder::new()
-        .name(format!("worker_{thread_idx}"))
-        .spawn(move || {
-          start_worker(WorkerData::new(thread_idx, globals));
-        })
-        .unwrap()
-    })
-    .collect();
+  type Game = G;
+
+  fn best_move(&mut self, game: &G, depth: u32) -> (Score, Option<G::Move>) {
+    debug_assert!(matches!(game.finished(), GameResult::NotFinished));
+    if depth == 0 {
+      return (Score::NO_INFO, None);
+    }
 
-  let mut any_bad = false;
-  for thread in thread_handles.into_iter() {
-    any_bad = thread.join().is_err() || any_bad;
-  }
-  assert!(!any_bad);
-
-  // for mv in game.each_move() {
-  //   let next_state = game.with_move(mv);
-  // print!("{mv}: ");
-  // if let Some(score) = globals.resolved_states_table().get(&next_state) {
-  //   println!("{score}");
-  //   if score.score_at_depth(score.determined_depth()) != ScoreValue::Tie {
-  //     playout(&next_state, globals.resolved_states_table(), score.determined_depth());
-  //   }
-  // } else {
-  //   println!();
-  // }
-  // }
-
-  globals.resolved_states_table().get(game).unwrap()
+    match self.solve(game, depth) {
+      Ok(result) => (result.score, result.best_move),
+      // There is no way to report errors through `Solver`, so pass on the
+      // panic as if the search had run on this thread.
+      Err(SolveError::WorkerPanicked { payload, .. }) => panic::resume_unwind(payload),
+      Err(err) => panic!("{err}"),
+    }
+  }
 }
 
 #[cfg(test)]
 mod tests {
-  use std::{collections::hash_map::RandomState, thread, time::SystemTime};
+  use std::{
+    collections::hash_map::RandomState,
+    env, fs, io, process,
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime},
+  };
 
-  use abstract_game::{Game, GameResult};
+  use abstract_game::{Game, GameResult, Score, ScoreValue, Solver};
 
   use crate::{
-    cooperate::{construct_globals, Options},
+    canonicalize::Canonicalize,
+    cooperate::{construct_globals, solve, CancelHandle, CooperativeSolver, Options, SolveError},
+    database::Database,
+    metrics::Metrics,
+    move_order::{HeuristicOrdering, KillerMoves, MoveOrderer},
+    principal_variation::ScoreTable,
     search_worker::{start_worker, WorkerData},
+    solvers::{heuristic::HeuristicAlphaBeta, ttable_solver::TTSolver},
     test::{
-      gomoku::Gomoku,
+      gomoku::{Gomoku, GomokuMove},
       nim::Nim,
       serial_search::{find_best_move_serial, find_best_move_serial_table},
       tic_tac_toe::Ttt,
@@ -187,6 +984,7 @@ mod tests {
         search_depth: STICKS + 1,
         num_threads: 1,
         unit_depth: 0,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -210,6 +1008,7 @@ mod tests {
         search_depth: STICKS + 1,
         num_threads: 2,
         unit_depth: 1,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -227,13 +1026,880 @@ mod tests {
       assert!(thread.join().is_ok());
     }
 
-    for sticks in 1..=(STICKS - 1) {
+    for sticks in 1..=STICKS {
       let cached_score = globals.resolved_states_table().get(&Nim::new(sticks));
       assert!(cached_score.is_some());
       assert_eq!(cached_score.unwrap(), Nim::new(sticks).expected_score());
     }
   }
 
+  #[test]
+  fn test_frontier_resolves_upper_levels() {
+    const DEPTH: u32 = 9;
+    let globals = construct_globals(
+      &Ttt::new(),
+      Options {
+        search_depth: DEPTH,
+        num_threads: 2,
+        unit_depth: 3,
+        ..Options::default()
+      },
+      RandomState::new(),
+    );
+    // The root and the states one and two moves from it are split, so only the
+    // states at the frontier are queued. Each of those is reached by playing
+    // X's two moves in either order, and the second path waits on the first.
+    assert_eq!(globals.stack_counts().split, 1 + 9 + 9 * 8);
+    assert_eq!(globals.stack_counts().queued, 9 * 8 * 7 / 2);
+    assert_eq!(globals.stack_counts().suspended, 9 * 8 * 7 / 2);
+
+    let thread_handles: Vec<_> = (0..2)
+      .map(|thread_idx| {
+        let globals = globals.clone();
+        thread::spawn(move || {
+          start_worker(WorkerData::new(thread_idx, globals));
+        })
+      })
+      .collect();
+    for thread in thread_handles.into_iter() {
+      assert!(thread.join().is_ok());
+    }
+
+    assert!(globals.finished());
+    let table = globals.resolved_states_table();
+    let mut upper_states = vec![Ttt::new()];
+    for _ in 0..3 {
+      for state in upper_states.iter() {
+        let score = table.get(state);
+        assert!(score.is_some(), "Missing score for\n{state}");
+        assert!(score
+          .unwrap()
+          .compatible(state.compute_expected_score(DEPTH)));
+      }
+      upper_states = upper_states
+        .iter()
+        .flat_map(|state| state.each_move().map(|m| state.with_move(m)))
+        .collect();
+    }
+  }
+
+  #[test]
+  fn test_solve_nim_frontier() {
+    const STICKS: u32 = 50;
+
+    for unit_depth in 0..=3 {
+      let result = solve(
+        &Nim::new(STICKS),
+        Options {
+          search_depth: STICKS + 1,
+          num_threads: 4,
+          unit_depth,
+          ..Options::default()
+        },
+      )
+      .unwrap();
+      assert_eq!(result.score, Nim::new(STICKS).expected_score());
+    }
+  }
+
+  #[test]
+  fn test_solve_nim_best_move() {
+    const STICKS: u32 = 20;
+
+    for sticks in 1..=STICKS {
+      let game = Nim::new(sticks);
+      let result = solve(
+        &game,
+        Options {
+          search_depth: sticks + 1,
+          num_threads: 2,
+          unit_depth: 1,
+          ..Options::default()
+        },
+      )
+      .unwrap();
+
+      assert!(result.best_move.is_some());
+      assert_eq!(
+        result.best_move,
+        result.principal_variation.first().copied()
+      );
+      if sticks % 3 != 0 {
+        // The winning move always leaves the opponent with a multiple of 3
+        // (possibly 0).
+        let next_state = game.with_move(result.best_move.unwrap());
+        if next_state.finished() == GameResult::NotFinished {
+          assert_eq!(
+            next_state.expected_score().score_at_depth(sticks),
+            ScoreValue::OtherPlayerWins
+          );
+        }
+      }
+
+      // The principal variation should play the game out to the end.
+      let final_state = result
+        .principal_variation
+        .iter()
+        .fold(game, |game, &m| game.with_move(m));
+      assert_ne!(final_state.finished(), GameResult::NotFinished);
+    }
+  }
+
+  #[test]
+  fn test_solve_nim_alpha_beta() {
+    const STICKS: u32 = 20;
+
+    for sticks in 1..=STICKS {
+      let game = Nim::new(sticks);
+      let depth = sticks + 1;
+      let result = solve(
+        &game,
+        Options {
+          search_depth: depth,
+          num_threads: 4,
+          unit_depth: 1,
+          alpha_beta: true,
+          ..Options::default()
+        },
+      )
+      .unwrap();
+
+      let expected = game.expected_score();
+      assert!(result.score.compatible(expected));
+      assert!(result.score.determined(depth));
+      assert_eq!(
+        result.score.score_at_depth(depth),
+        expected.score_at_depth(depth)
+      );
+      assert!(result.best_move.is_some());
+      if sticks % 3 != 0 {
+        let next_state = game.with_move(result.best_move.unwrap());
+        if next_state.finished() == GameResult::NotFinished {
+          assert_eq!(
+            next_state.expected_score().score_at_depth(sticks),
+            ScoreValue::OtherPlayerWins
+          );
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_solve_alpha_beta_prunes() {
+    const DEPTH: u32 = 9;
+    let options = Options {
+      search_depth: DEPTH,
+      num_threads: 1,
+      ..Options::default()
+    };
+    let full = solve(&Ttt::new(), options.clone()).unwrap();
+    let pruned = solve(
+      &Ttt::new(),
+      Options {
+        alpha_beta: true,
+        ..options
+      },
+    )
+    .unwrap();
+
+    assert!(pruned
+      .score
+      .compatible(Ttt::new().compute_expected_score(DEPTH)));
+    assert_eq!(full.metrics.cutoffs, 0);
+    assert!(pruned.metrics.cutoffs > 0);
+    assert!(pruned.metrics.nodes < full.metrics.nodes);
+  }
+
+  #[test]
+  fn test_solve_balanced() {
+    const DEPTH: u32 = 9;
+    for units_per_thread in [1, 4, 100] {
+      let result = solve(
+        &Ttt::new(),
+        Options {
+          search_depth: DEPTH,
+          num_threads: 4,
+          units_per_thread: Some(units_per_thread),
+          balance_playouts: Some(8),
+          ..Options::default()
+        },
+      )
+      .unwrap();
+      assert!(result
+        .score
+        .compatible(Ttt::new().compute_expected_score(DEPTH)));
+    }
+  }
+
+  #[test]
+  fn test_solve_transposition_driven() {
+    const DEPTH: u32 = 7;
+    let options = Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    };
+    let gomoku = Gomoku::new(4, 4, 3);
+    let shared = CooperativeSolver::new(options.clone())
+      .solve(&gomoku, DEPTH)
+      .unwrap();
+    let routed = CooperativeSolver::new(Options {
+      transposition_driven: true,
+      ..options
+    })
+    .solve(&gomoku, DEPTH)
+    .unwrap();
+
+    assert_eq!(routed.score, shared.score);
+    assert_eq!(shared.metrics.routes, 0);
+    assert!(routed.metrics.routes > 0);
+    assert_eq!(routed.metrics.steals, 0);
+  }
+
+  #[test]
+  fn test_solve_nim_transposition_driven() {
+    const STICKS: u32 = 50;
+
+    for num_threads in [1, 2, 4] {
+      let result = solve(
+        &Nim::new(STICKS),
+        Options {
+          search_depth: STICKS + 1,
+          num_threads,
+          unit_depth: 2,
+          transposition_driven: true,
+          ..Options::default()
+        },
+      )
+      .unwrap();
+      assert_eq!(result.score, Nim::new(STICKS).expected_score());
+      assert_eq!(result.metrics.steals, 0);
+    }
+  }
+
+  #[test]
+  fn test_move_ordering_prunes() {
+    const DEPTH: u32 = 7;
+    let options = Options {
+      num_threads: 1,
+      alpha_beta: true,
+      ..Options::default()
+    };
+    let gomoku = Gomoku::new(4, 4, 3);
+    let unordered = CooperativeSolver::new(options.clone())
+      .solve(&gomoku, DEPTH)
+      .unwrap();
+    let ordered = CooperativeSolver::new(options)
+      .with_move_orderer((HeuristicOrdering, KillerMoves::new()))
+      .solve(&gomoku, DEPTH)
+      .unwrap();
+
+    assert!(ordered.score.compatible(unordered.score));
+    assert!(ordered.metrics.cutoffs > 0);
+    assert!(
+      ordered.metrics.nodes < unordered.metrics.nodes,
+      "{} vs {}",
+      ordered.metrics.nodes,
+      unordered.metrics.nodes
+    );
+  }
+
+  #[test]
+  fn test_immediate_wins() {
+    const DEPTH: u32 = 7;
+    let options = Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    };
+    let gomoku = Gomoku::new(4, 4, 3);
+    let full = CooperativeSolver::new(options.clone())
+      .solve(&gomoku, DEPTH)
+      .unwrap();
+    let short_circuited = CooperativeSolver::new(options)
+      .with_immediate_wins()
+      .solve(&gomoku, DEPTH)
+      .unwrap();
+
+    assert_eq!(short_circuited.score, full.score);
+    assert_eq!(full.metrics.immediate_wins, 0);
+    assert!(short_circuited.metrics.immediate_wins > 0);
+    assert!(short_circuited.metrics.nodes < full.metrics.nodes);
+  }
+
+  #[test]
+  fn test_evaluation_matches_serial() {
+    const DEPTH: u32 = 4;
+    let gomoku = Gomoku::new(4, 4, 3)
+      .with_move(GomokuMove::new(1, 1))
+      .with_move(GomokuMove::new(0, 0));
+    let (_, expected_evaluation, _) = HeuristicAlphaBeta::new().best_move_evaluated(&gomoku, DEPTH);
+
+    for (num_threads, unit_depth) in [(1, 0), (4, 0), (4, 1)] {
+      let options = Options {
+        num_threads,
+        unit_depth,
+        ..Options::default()
+      };
+      let unevaluated = CooperativeSolver::new(options.clone())
+        .solve(&gomoku, DEPTH)
+        .unwrap();
+      let evaluated = CooperativeSolver::new(options)
+        .with_evaluation()
+        .solve(&gomoku, DEPTH)
+        .unwrap();
+
+      assert_eq!(unevaluated.evaluation, None);
+      assert_eq!(evaluated.evaluation, Some(expected_evaluation));
+      assert_eq!(evaluated.score, unevaluated.score);
+      assert!((1..=DEPTH as usize).contains(&evaluated.principal_variation.len()));
+      assert_eq!(
+        evaluated.principal_variation.first().copied(),
+        evaluated.best_move
+      );
+    }
+  }
+
+  #[test]
+  fn test_evaluation_blocks_loss() {
+    // O threatens to win at (4, 1), which X must block.
+    let gomoku = [(0, 1), (1, 1), (4, 4), (2, 1), (0, 4), (3, 1)]
+      .into_iter()
+      .fold(Gomoku::new(5, 5, 4), |gomoku, (x, y)| {
+        gomoku.with_move(GomokuMove::new(x, y))
+      });
+    let result = CooperativeSolver::new(Options {
+      num_threads: 2,
+      ..Options::default()
+    })
+    .with_evaluation()
+    .solve(&gomoku, 2)
+    .unwrap();
+
+    assert!(!result.evaluation.unwrap().is_proven());
+    assert_eq!(result.best_move, Some(GomokuMove::new(4, 1)));
+  }
+
+  #[test]
+  fn test_solver_nim() {
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    });
+
+    for sticks in 1..=20 {
+      let (score, best_move) = solver.best_move(&Nim::new(sticks), sticks + 1);
+      assert_eq!(score, Nim::new(sticks).expected_score());
+      assert_eq!(solver.cached_score(&Nim::new(sticks)), Some(score));
+      assert!(best_move.is_some());
+      if sticks % 3 != 0 {
+        let next_state = Nim::new(sticks).with_move(best_move.unwrap());
+        if next_state.finished() == GameResult::NotFinished {
+          assert_eq!(
+            next_state.expected_score().score_at_depth(sticks),
+            ScoreValue::OtherPlayerWins
+          );
+        }
+      }
+    }
+
+    assert_eq!(solver.best_move(&Nim::new(5), 0), (Score::NO_INFO, None));
+  }
+
+  #[test]
+  fn test_solver_reuses_table() {
+    const DEPTH: u32 = 10;
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 4,
+      unit_depth: 1,
+      ..Options::default()
+    });
+
+    // Searching shallower first should only add to what the deeper search
+    // finds.
+    for depth in [3, DEPTH] {
+      let (score, _) = solver.best_move(&Ttt::new(), depth);
+      assert!(score.compatible(Ttt::new().compute_expected_score(depth)));
+    }
+
+    // Every state below the root is now resolved, so searching from any of
+    // them finds the same scores as before.
+    let mut ttt = Ttt::new();
+    for depth in (1..DEPTH).rev() {
+      if ttt.finished() != GameResult::NotFinished {
+        break;
+      }
+      let cached_score = solver.cached_score(&ttt);
+      let (score, best_move) = solver.best_move(&ttt, depth);
+      assert_eq!(Some(score), cached_score);
+      ttt.make_move(best_move.unwrap());
+    }
+  }
+
+  #[test]
+  fn test_solve_iterative_nim() {
+    const STICKS: u32 = 20;
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    });
+
+    let mut depths = Vec::new();
+    let result = solver
+      .solve_iterative(&Nim::new(STICKS), 2 * STICKS, |depth, result| {
+        assert!(result.score.compatible(Nim::new(STICKS).expected_score()));
+        assert_eq!(
+          result.best_move,
+          result.principal_variation.first().copied()
+        );
+        depths.push(depth);
+      })
+      .unwrap();
+
+    // The game can't last longer than `STICKS` turns, so the search should stop
+    // well before the maximum depth.
+    assert_eq!(depths, (1..=depths.len() as u32).collect::<Vec<_>>());
+    assert!(depths.len() as u32 <= STICKS + 1);
+    assert!(result.score.fully_determined());
+    assert_eq!(result.score, Nim::new(STICKS).expected_score());
+  }
+
+  #[test]
+  fn test_solve_iterative_ttt() {
+    const DEPTH: u32 = 10;
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    });
+
+    let mut iterations = 0;
+    let result = solver
+      .solve_iterative(&Ttt::new(), DEPTH, |depth, result| {
+        assert!(result
+          .score
+          .compatible(Ttt::new().compute_expected_score(depth)));
+        iterations += 1;
+      })
+      .unwrap();
+    assert!(iterations <= DEPTH);
+    assert!(result
+      .score
+      .compatible(Ttt::new().compute_expected_score(DEPTH)));
+  }
+
+  #[test]
+  fn test_options_builder() {
+    let options = Options::builder()
+      .num_threads(3)
+      .search_depth(6)
+      .unit_depth(2)
+      .alpha_beta(true)
+      .build()
+      .unwrap();
+    assert_eq!(options.num_threads, 3);
+    assert_eq!(options.search_depth, 6);
+    assert_eq!(options.unit_depth, 2);
+    assert!(options.alpha_beta);
+
+    assert!(matches!(
+      Options::builder().num_threads(0).build(),
+      Err(SolveError::InvalidOptions(_))
+    ));
+    assert!(matches!(
+      Options::builder().search_depth(3).unit_depth(3).build(),
+      Err(SolveError::InvalidOptions(_))
+    ));
+    assert!(matches!(
+      Options::builder().units_per_thread(0).build(),
+      Err(SolveError::InvalidOptions(_))
+    ));
+    assert!(matches!(
+      Options::builder().balance_playouts(0).build(),
+      Err(SolveError::InvalidOptions(_))
+    ));
+    // The depth of each search can be left for the solver to choose.
+    assert!(Options::builder().unit_depth(3).build().is_ok());
+    // `unit_depth` is ignored when the frontier is sized by `units_per_thread`.
+    assert!(Options::builder()
+      .search_depth(3)
+      .unit_depth(3)
+      .units_per_thread(2)
+      .build()
+      .is_ok());
+  }
+
+  #[test]
+  fn test_solve_invalid_options() {
+    assert!(matches!(
+      solve(&Ttt::new(), Options::default()),
+      Err(SolveError::InvalidOptions(_))
+    ));
+    assert!(matches!(
+      solve(
+        &Ttt::new(),
+        Options {
+          num_threads: 0,
+          search_depth: 4,
+          ..Options::default()
+        }
+      ),
+      Err(SolveError::InvalidOptions(_))
+    ));
+    assert!(matches!(
+      solve(
+        &Ttt::new(),
+        Options {
+          search_depth: 2,
+          unit_depth: 5,
+          ..Options::default()
+        }
+      ),
+      Err(SolveError::InvalidOptions(_))
+    ));
+    assert!(matches!(
+      CooperativeSolver::new(Options::default()).solve_iterative(&Ttt::new(), 0, |_, _| {
+        panic!("No iterations should run")
+      }),
+      Err(SolveError::InvalidOptions(_))
+    ));
+  }
+
+  /// Panics when asked to order the moves of a state searched to `DEPTH`.
+  struct PanickingOrderer<const DEPTH: u32>;
+
+  impl<G: Game, const DEPTH: u32> MoveOrderer<G> for PanickingOrderer<DEPTH> {
+    fn order_moves(&self, _: &G, depth: u32, _: Option<&dyn ScoreTable<G>>, _: &mut [G::Move]) {
+      if depth == DEPTH {
+        panic!("failed to order moves");
+      }
+    }
+  }
+
+  #[test]
+  fn test_solve_worker_panic() {
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    })
+    .with_move_orderer(PanickingOrderer::<3>);
+
+    let err = solver.solve(&Ttt::new(), 7).err().unwrap();
+    assert!(matches!(err, SolveError::WorkerPanicked { .. }));
+    assert!(
+      err.to_string().ends_with("panicked: failed to order moves"),
+      "{err}"
+    );
+    // The states resolved by the aborted search are discarded.
+    assert!(solver.table().is_none());
+
+    // Searches which don't reach the panicking depth still succeed.
+    let result = solver.solve(&Ttt::new(), 2).unwrap();
+    assert!(result.complete);
+  }
+
+  #[test]
+  fn test_solve_cancelled() {
+    let cancel_handle = CancelHandle::new();
+    cancel_handle.cancel();
+
+    let result = solve(
+      &Ttt::new(),
+      Options {
+        search_depth: 10,
+        num_threads: 4,
+        unit_depth: 2,
+        cancel_handle: Some(cancel_handle),
+        ..Options::default()
+      },
+    )
+    .unwrap();
+    assert!(!result.complete);
+    assert_eq!(result.score, Score::NO_INFO);
+  }
+
+  #[test]
+  fn test_solve_node_limit() {
+    const DEPTH: u32 = 10;
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 4,
+      unit_depth: 2,
+      node_limit: Some(2000),
+      ..Options::default()
+    });
+
+    // Each search gets its own budget, and the abandoned search should leave
+    // nothing behind for the next one.
+    for _ in 0..3 {
+      let result = solver.solve(&Ttt::new(), DEPTH).unwrap();
+      assert!(!result.complete);
+    }
+
+    // Abandoned frames are never committed, so everything in the table should
+    // still be correct.
+    let globals = solver.globals.as_ref().unwrap();
+    for state in globals.resolved_states_table().table().iter() {
+      assert!(
+        state
+          .value()
+          .compatible(state.key().compute_expected_score(DEPTH)),
+        "Expect computed score {} to be compatible with true score {}",
+        state.value(),
+        state.key().compute_expected_score(DEPTH)
+      );
+    }
+  }
+
+  #[test]
+  fn test_solve_time_limit() {
+    let start = SystemTime::now();
+    let result = solve(
+      &Gomoku::new(5, 5, 4),
+      Options {
+        search_depth: 16,
+        num_threads: 4,
+        unit_depth: 3,
+        time_limit: Some(Duration::from_millis(100)),
+        ..Options::default()
+      },
+    )
+    .unwrap();
+    assert!(!result.complete);
+    assert!(SystemTime::now().duration_since(start).unwrap() < Duration::from_secs(10));
+  }
+
+  #[test]
+  fn test_solve_cancel_from_other_thread() {
+    let cancel_handle = CancelHandle::new();
+    let canceller = {
+      let cancel_handle = cancel_handle.clone();
+      thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        cancel_handle.cancel();
+      })
+    };
+
+    let result = solve(
+      &Gomoku::new(5, 5, 4),
+      Options {
+        search_depth: 16,
+        num_threads: 4,
+        unit_depth: 3,
+        cancel_handle: Some(cancel_handle),
+        ..Options::default()
+      },
+    )
+    .unwrap();
+    assert!(canceller.join().is_ok());
+    assert!(!result.complete);
+  }
+
+  #[test]
+  fn test_solve_metrics() {
+    const THREADS: u32 = 4;
+    let result = solve(
+      &Ttt::new(),
+      Options {
+        search_depth: 10,
+        num_threads: THREADS,
+        unit_depth: 2,
+        ..Options::default()
+      },
+    )
+    .unwrap();
+
+    assert_eq!(result.worker_metrics.len(), THREADS as usize);
+    assert_eq!(
+      result.metrics,
+      result.worker_metrics.iter().cloned().sum::<Metrics>()
+    );
+    assert!(result.metrics.nodes > result.metrics.terminal_states);
+    assert!(result.metrics.terminal_states > 0);
+    assert!(result.metrics.commits > 0);
+    assert!(result.metrics.claims >= result.metrics.commits);
+    assert!(result
+      .worker_metrics
+      .iter()
+      .all(|metrics| !metrics.wall_time.is_zero()));
+  }
+
+  #[test]
+  fn test_solve_bounded_table() {
+    const DEPTH: u32 = 10;
+    const CAPACITY: usize = 256;
+    let result = solve(
+      &Ttt::new(),
+      Options {
+        search_depth: DEPTH,
+        num_threads: 4,
+        unit_depth: 3,
+        table_capacity: Some(CAPACITY),
+        ..Options::default()
+      },
+    )
+    .unwrap();
+
+    assert!(result.complete);
+    assert!(result
+      .score
+      .compatible(Ttt::new().compute_expected_score(DEPTH)));
+  }
+
+  #[test]
+  fn test_solver_bounded_table() {
+    const CAPACITY: usize = 128;
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 4,
+      unit_depth: 2,
+      table_capacity: Some(CAPACITY),
+      ..Options::default()
+    });
+
+    for depth in 1..=9 {
+      let (score, _) = solver.best_move(&Ttt::new(), depth);
+      assert!(score.compatible(Ttt::new().compute_expected_score(depth)));
+    }
+
+    let entries = solver
+      .globals
+      .as_ref()
+      .unwrap()
+      .resolved_states_table()
+      .entries();
+    assert!(entries.len() <= CAPACITY);
+    for (state, score) in entries {
+      assert!(score.compatible(state.compute_expected_score(9)));
+    }
+  }
+
+  #[test]
+  fn test_save_and_load_table() {
+    const DEPTH: u32 = 9;
+    for table_capacity in [None, Some(1 << 12)] {
+      let options = Options {
+        num_threads: 4,
+        unit_depth: 2,
+        table_capacity,
+        ..Options::default()
+      };
+      let mut solver = CooperativeSolver::new(options.clone());
+      let (score, _) = solver.best_move(&Ttt::new(), DEPTH);
+
+      let mut bytes = Vec::new();
+      solver.save_table(&mut bytes).unwrap();
+
+      let mut loaded = CooperativeSolver::new(options);
+      assert_eq!(loaded.load_table(bytes.as_slice()).unwrap(), DEPTH);
+      assert_eq!(loaded.cached_score(&Ttt::new()), Some(score));
+      assert_eq!(loaded.best_move(&Ttt::new(), DEPTH).0, score);
+
+      // The serial solvers read the same format.
+      let mut serial = TTSolver::<Ttt, _>::new();
+      assert_eq!(serial.load_table(bytes.as_slice()).unwrap(), DEPTH);
+      assert_eq!(serial.table().get(&Ttt::new()), Some(&score));
+    }
+  }
+
+  #[test]
+  fn test_solver_with_database() {
+    const DEPTH: u32 = 9;
+    let options = Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    };
+
+    let mut solver = CooperativeSolver::new(options.clone());
+    let expected = solver.solve(&Ttt::new(), DEPTH).unwrap();
+    let mut table = Vec::new();
+    solver.save_table(&mut table).unwrap();
+
+    let path = env::temp_dir().join(format!(
+      "cooperate-{}-test_solver_with_database",
+      process::id()
+    ));
+    Database::<Ttt>::build_from_table(table.as_slice(), fs::File::create(&path).unwrap()).unwrap();
+    let database = Database::open(&path);
+    fs::remove_file(&path).unwrap();
+    let database = Arc::new(database.unwrap());
+
+    // Every state searched before is in the database, so the search should
+    // stop at the frontier.
+    let mut solver = CooperativeSolver::new(options);
+    solver.set_database(Some(database));
+    let result = solver.solve(&Ttt::new(), DEPTH).unwrap();
+    assert_eq!(result.score, expected.score);
+    assert!(result.metrics.database_hits > 0);
+    assert!(result.metrics.nodes < expected.metrics.nodes);
+  }
+
+  #[test]
+  fn test_solver_canonicalization() {
+    const DEPTH: u32 = 9;
+    let options = Options {
+      num_threads: 4,
+      unit_depth: 2,
+      ..Options::default()
+    };
+    let mut solver = CooperativeSolver::new(options.clone());
+    let mut canonical_solver = CooperativeSolver::new(options).with_canonicalization();
+
+    let expected = solver.solve(&Ttt::new(), DEPTH).unwrap();
+    let result = canonical_solver.solve(&Ttt::new(), DEPTH).unwrap();
+    assert_eq!(result.score, expected.score);
+    assert!(result.metrics.commits < expected.metrics.commits);
+
+    let entries = canonical_solver
+      .globals
+      .as_ref()
+      .unwrap()
+      .resolved_states_table()
+      .entries();
+    for (state, score) in entries {
+      assert_eq!(state.canonicalize(), state);
+      assert!(score.compatible(state.compute_expected_score(DEPTH)));
+    }
+
+    // The best moves are moves of the state that was passed in, not of its
+    // canonical representative.
+    let mut ttt = Ttt::new();
+    for depth in (2..=DEPTH).rev() {
+      let (score, best_move) = canonical_solver.best_move(&ttt, depth);
+      let best_move = best_move.unwrap();
+      assert!(ttt.each_move().any(|m| m == best_move));
+      ttt.make_move(best_move);
+      if ttt.finished() != GameResult::NotFinished {
+        break;
+      }
+      assert!(score.compatible(ttt.compute_expected_score(depth - 1).backstep()));
+    }
+  }
+
+  #[test]
+  fn test_solver_zobrist_keys() {
+    const DEPTH: u32 = 9;
+    let expected = Ttt::new().compute_expected_score(DEPTH);
+    for table_capacity in [None, Some(1 << 10)] {
+      let mut solver = CooperativeSolver::new(Options {
+        num_threads: 4,
+        unit_depth: 2,
+        table_capacity,
+        ..Options::default()
+      })
+      .with_zobrist_keys();
+
+      let (score, _) = solver.best_move(&Ttt::new(), DEPTH);
+      assert!(score.compatible(expected));
+      assert_eq!(solver.cached_score(&Ttt::new()), Some(score));
+
+      let err = solver.save_table(Vec::new()).unwrap_err();
+      assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+  }
+
   #[test]
   fn test_ttt_p2() {
     const DEPTH: u32 = 10;
@@ -245,6 +1911,7 @@ mod tests {
         search_depth: DEPTH,
         num_threads: THREADS,
         unit_depth: 1,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -298,6 +1965,7 @@ mod tests {
         search_depth: DEPTH,
         num_threads: THREADS,
         unit_depth: 2,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -352,6 +2020,7 @@ mod tests {
         search_depth: DEPTH,
         num_threads: THREADS,
         unit_depth: 3,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -416,6 +2085,7 @@ mod tests {
         search_depth: DEPTH,
         num_threads: THREADS,
         unit_depth: 3,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -480,6 +2150,7 @@ mod tests {
         search_depth: DEPTH,
         num_threads: THREADS,
         unit_depth: 5,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -544,6 +2215,7 @@ mod tests {
         search_depth: DEPTH,
         num_threads: THREADS,
         unit_depth: 5,
+        ..Options::default()
       },
       RandomState::new(),
     );
@@ -595,4 +2267,40 @@ mod tests {
       );
     }
   }
+
+  /// Compares solving gomoku with every worker sharing every state against
+  /// routing each state to the worker which owns it.
+  #[test]
+  #[ignore]
+  fn test_gomoku_transposition_driven_benchmark() {
+    const DEPTH: u32 = 16;
+    const THREADS: u32 = 8;
+
+    let mut scores = Vec::new();
+    for transposition_driven in [false, true] {
+      let start = SystemTime::now();
+      let result = solve(
+        &Gomoku::new(4, 4, 4),
+        Options {
+          search_depth: DEPTH,
+          num_threads: THREADS,
+          unit_depth: 3,
+          transposition_driven,
+          ..Options::default()
+        },
+      )
+      .unwrap();
+      let end = SystemTime::now();
+      println!(
+        "transposition_driven: {transposition_driven}: {:?}, {} nodes, {} steals, {} routes",
+        end.duration_since(start).unwrap(),
+        result.metrics.nodes,
+        result.metrics.steals,
+        result.metrics.routes
+      );
+      scores.push(result.score);
+    }
+
+    assert_eq!(scores[0], scores[1]);
+  }
 }
@@ -0,0 +1,362 @@
+use std::{
+  cmp::Ordering,
+  fs::File,
+  io::{self, Read, Write},
+  marker::PhantomData,
+  path::Path,
+};
+
+use abstract_game::Score;
+use memmap2::Mmap;
+
+use crate::persist::{
+  invalid_data, read_header, read_score, read_table, write_header, write_score, EncodeGame,
+};
+
+/// Identifies files written by `Database::build`.
+const DATABASE_MAGIC: [u8; 8] = *b"COOPEDB\0";
+
+/// Each entry is the key of a game followed by its encoded score.
+const KEY_SIZE: usize = 8;
+const SCORE_SIZE: usize = 9;
+const ENTRY_SIZE: usize = KEY_SIZE + SCORE_SIZE;
+
+/// Computes the key of `game` in a database, which is a 64-bit FNV-1a hash of
+/// its encoding. Unlike the hashers used for in-memory tables, this is the same
+/// in every process and on every platform.
+fn stable_hash<G: EncodeGame>(game: &G) -> u64 {
+  struct Fnv1a(u64);
+
+  impl Write for Fnv1a {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      for &byte in buf {
+        self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+      }
+      Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+  game
+    .encode(&mut hasher)
+    .expect("Encoding to a hasher cannot fail.");
+  hasher.0
+}
+
+/// A read-only database of game scores, memory-mapped from a file built by
+/// `Database::build`. Entries are sorted by the stable hash of their game, so
+/// lookups binary search the file directly and only the pages they touch are
+/// read into memory. The file can be shared by any number of processes.
+///
+/// Only the hash of each game is stored, so games which share a hash are left
+/// out of the database when it is built rather than risking a wrong score.
+pub struct Database<G> {
+  mmap: Mmap,
+  /// The offset of the first entry in `mmap`.
+  entries_start: usize,
+  len: usize,
+  search_depth: u32,
+  /// Computes the key of a game. This is captured when the database is opened
+  /// so lookups don't require `G: EncodeGame`.
+  key: fn(&G) -> u64,
+  _game: PhantomData<fn(&G)>,
+}
+
+impl<G> Database<G>
+where
+  G: EncodeGame + Eq,
+{
+  /// Writes a database of `entries` to `writer`, recording that the scores were
+  /// found by searches to at most `search_depth`. Scores of games which appear
+  /// more than once are merged.
+  pub fn build<W: Write>(
+    mut writer: W,
+    search_depth: u32,
+    entries: impl IntoIterator<Item = (G, Score)>,
+  ) -> io::Result<()> {
+    let mut entries: Vec<_> = entries
+      .into_iter()
+      .map(|(game, score)| (stable_hash(&game), game, score))
+      .collect();
+    entries.sort_unstable_by_key(|(key, _, _)| *key);
+
+    let mut merged: Vec<(u64, Score)> = Vec::with_capacity(entries.len());
+    for group in entries.chunk_by(|(key1, _, _), (key2, _, _)| key1 == key2) {
+      let (key, game, score) = &group[0];
+      let mut score = *score;
+      let mut collision = false;
+      for (_, other_game, other_score) in &group[1..] {
+        if other_game == game {
+          score = score.merge(*other_score);
+        } else {
+          collision = true;
+        }
+      }
+      if !collision {
+        merged.push((*key, score));
+      }
+    }
+
+    write_header::<G, _>(
+      &mut writer,
+      &DATABASE_MAGIC,
+      search_depth,
+      merged.len() as u64,
+    )?;
+    for (key, score) in merged {
+      writer.write_all(&key.to_le_bytes())?;
+      write_score(&mut writer, score)?;
+    }
+    writer.flush()
+  }
+
+  /// Converts a table saved with `PersistTable::save` in `reader` into a
+  /// database written to `writer`.
+  pub fn build_from_table<R: Read, W: Write>(reader: R, writer: W) -> io::Result<()> {
+    let mut entries = Vec::new();
+    let search_depth = read_table(reader, |game: G, score| entries.push((game, score)))?;
+    Self::build(writer, search_depth, entries)
+  }
+
+  /// Memory-maps the database at `path`. The file must not be modified while
+  /// the database is open.
+  pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+    let file = File::open(path)?;
+    // Safety: databases are never written to after they are built, and callers
+    // are required not to modify the file while it is mapped.
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    let mut header: &[u8] = &mmap;
+    let (search_depth, len) = read_header::<G, _>(&mut header, &DATABASE_MAGIC)?;
+    let entries_start = mmap.len() - header.len();
+    if len.checked_mul(ENTRY_SIZE as u64) != Some(header.len() as u64) {
+      return Err(invalid_data(format!(
+        "database should have {len} entries, but has {} bytes of entries",
+        header.len()
+      )));
+    }
+
+    Ok(Self {
+      entries_start,
+      len: len as usize,
+      search_depth,
+      mmap,
+      key: stable_hash::<G>,
+      _game: PhantomData,
+    })
+  }
+}
+
+impl<G> Database<G> {
+  /// The depth of the searches the scores in this database were found by.
+  pub fn search_depth(&self) -> u32 {
+    self.search_depth
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  fn entry(&self, idx: usize) -> &[u8] {
+    let start = self.entries_start + idx * ENTRY_SIZE;
+    &self.mmap[start..start + ENTRY_SIZE]
+  }
+
+  fn key_at(&self, idx: usize) -> u64 {
+    u64::from_le_bytes(self.entry(idx)[..KEY_SIZE].try_into().unwrap())
+  }
+
+  /// Returns the score of `game`, if it is in the database.
+  pub fn get(&self, game: &G) -> Option<Score> {
+    let key = (self.key)(game);
+    let (mut lo, mut hi) = (0, self.len);
+    while lo < hi {
+      let mid = lo + (hi - lo) / 2;
+      match self.key_at(mid).cmp(&key) {
+        Ordering::Less => lo = mid + 1,
+        Ordering::Greater => hi = mid,
+        Ordering::Equal => {
+          let mut score: &[u8] = &self.entry(mid)[KEY_SIZE..];
+          return read_score(&mut score).ok();
+        }
+      }
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+  };
+
+  use abstract_game::{Score, Solver};
+
+  use crate::{
+    database::{stable_hash, Database, DATABASE_MAGIC},
+    persist::{write_header, EncodeGame, PersistTable},
+    solvers::ttable_solver::TTSolver,
+    test::{nim::Nim, tic_tac_toe::Ttt},
+  };
+
+  /// A path in the temp directory which is removed when dropped.
+  struct TempPath(PathBuf);
+
+  impl TempPath {
+    fn new(name: &str) -> Self {
+      Self(env::temp_dir().join(format!("cooperate-{}-{name}", process::id())))
+    }
+  }
+
+  impl Drop for TempPath {
+    fn drop(&mut self) {
+      let _ = fs::remove_file(&self.0);
+    }
+  }
+
+  #[test]
+  fn test_database_matches_table() {
+    let mut solver = TTSolver::new();
+    solver.best_move(&Ttt::new(), 9);
+    let path = TempPath::new("test_database_matches_table");
+    Database::build(
+      fs::File::create(&path.0).unwrap(),
+      9,
+      solver
+        .table()
+        .iter()
+        .map(|(game, &score)| (game.clone(), score)),
+    )
+    .unwrap();
+
+    let database = Database::<Ttt>::open(&path.0).unwrap();
+    assert_eq!(database.search_depth(), 9);
+    assert_eq!(database.len(), solver.table().len());
+    for (game, &score) in solver.table() {
+      assert_eq!(database.get(game), Some(score));
+    }
+  }
+
+  #[test]
+  fn test_build_from_table() {
+    let mut solver = TTSolver::new();
+    solver.best_move(&Nim::new(30), 30);
+    let mut table = Vec::new();
+    solver.table().save(&mut table, 30).unwrap();
+
+    let path = TempPath::new("test_build_from_table");
+    Database::<Nim>::build_from_table(table.as_slice(), fs::File::create(&path.0).unwrap())
+      .unwrap();
+
+    let database = Database::<Nim>::open(&path.0).unwrap();
+    assert_eq!(database.search_depth(), 30);
+    for (game, &score) in solver.table() {
+      assert_eq!(database.get(game), Some(score));
+    }
+    assert_eq!(database.get(&Nim::new(31)), None);
+  }
+
+  #[test]
+  fn test_duplicates_merged() {
+    let path = TempPath::new("test_duplicates_merged");
+    Database::build(
+      fs::File::create(&path.0).unwrap(),
+      5,
+      [(Nim::new(4), Score::tie(2)), (Nim::new(4), Score::win(5))],
+    )
+    .unwrap();
+
+    let database = Database::open(&path.0).unwrap();
+    assert_eq!(database.len(), 1);
+    assert_eq!(
+      database.get(&Nim::new(4)),
+      Some(Score::tie(2).merge(Score::win(5)))
+    );
+  }
+
+  #[test]
+  fn test_collisions_dropped() {
+    /// Encodes only the parity of the number of sticks, so every other game
+    /// collides.
+    #[derive(PartialEq, Eq)]
+    struct Parity(u32);
+
+    impl EncodeGame for Parity {
+      const GAME_TYPE: &'static str = "parity";
+
+      fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[(self.0 % 2) as u8])
+      }
+
+      fn decode<R: io::Read>(_reader: &mut R) -> io::Result<Self> {
+        Err(io::Error::other("Parity cannot be decoded"))
+      }
+    }
+
+    assert_eq!(stable_hash(&Parity(1)), stable_hash(&Parity(3)));
+    let path = TempPath::new("test_collisions_dropped");
+    Database::build(
+      fs::File::create(&path.0).unwrap(),
+      5,
+      [
+        (Parity(1), Score::tie(2)),
+        (Parity(3), Score::win(5)),
+        (Parity(2), Score::lose(3)),
+      ],
+    )
+    .unwrap();
+
+    let database = Database::open(&path.0).unwrap();
+    assert_eq!(database.get(&Parity(1)), None);
+    assert_eq!(database.get(&Parity(3)), None);
+    assert_eq!(database.get(&Parity(2)), Some(Score::lose(3)));
+  }
+
+  #[test]
+  fn test_open_wrong_game() {
+    let path = TempPath::new("test_open_wrong_game");
+    Database::build(
+      fs::File::create(&path.0).unwrap(),
+      5,
+      [(Nim::new(4), Score::tie(2))],
+    )
+    .unwrap();
+
+    let err = Database::<Ttt>::open(&path.0).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn test_open_truncated() {
+    let path = TempPath::new("test_open_truncated");
+    let mut bytes = Vec::new();
+    Database::build(&mut bytes, 5, [(Nim::new(4), Score::tie(2))]).unwrap();
+    fs::write(&path.0, &bytes[..bytes.len() - 1]).unwrap();
+
+    let err = Database::<Nim>::open(&path.0).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn test_open_huge_len() {
+    let path = TempPath::new("test_open_huge_len");
+    let mut bytes = Vec::new();
+    write_header::<Nim, _>(&mut bytes, &DATABASE_MAGIC, 5, u64::MAX).unwrap();
+    fs::write(&path.0, &bytes).unwrap();
+
+    let err = Database::<Nim>::open(&path.0).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+use abstract_game::{Game, Score, ScoreValue};
+
+/// Games which can estimate how good a state is without searching it. Searches
+/// which stop at a depth horizon use this to choose between moves whose scores
+/// they couldn't determine, so they can play games too large to solve.
+pub trait Evaluate: Game {
+  /// Returns an estimate of how good this state is for the player to move,
+  /// where higher is better. The estimate for the other player is the negation
+  /// of this one. Estimates are clamped to `Evaluation::MAX_HEURISTIC`, so they
+  /// always rank below proven wins and above proven losses.
+  fn evaluate(&self) -> i32;
+}
+
+/// The value of a state from the perspective of the player to move. States
+/// whose result is proven are valued by it, with faster wins and slower losses
+/// ranking higher, and every other state is valued by a heuristic estimate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Evaluation(i32);
+
+impl Evaluation {
+  /// The value of winning on the spot. Winning in `n` turns is worth `WIN - n`.
+  const WIN: i32 = 1 << 30;
+  /// The largest magnitude of a heuristic estimate.
+  pub const MAX_HEURISTIC: i32 = 1 << 29;
+
+  /// The evaluation of a state with `score`, falling back to `heuristic` if the
+  /// score doesn't prove a result.
+  pub fn new(score: Score, heuristic: i32) -> Self {
+    Self::proven(score).unwrap_or_else(|| Self::heuristic(heuristic))
+  }
+
+  /// The evaluation of a state with `score`, if the score proves a result.
+  pub fn proven(score: Score) -> Option<Self> {
+    let turns = score.determined_depth().min(Self::MAX_HEURISTIC as u32) as i32;
+    match score.score() {
+      ScoreValue::CurrentPlayerWins => Some(Self(Self::WIN - turns)),
+      ScoreValue::OtherPlayerWins => Some(Self(-Self::WIN + turns)),
+      ScoreValue::Tie if score.fully_determined() => Some(Self(0)),
+      ScoreValue::Tie => None,
+    }
+  }
+
+  /// A heuristic estimate, clamped to `MAX_HEURISTIC`.
+  pub fn heuristic(value: i32) -> Self {
+    Self(value.clamp(-Self::MAX_HEURISTIC, Self::MAX_HEURISTIC))
+  }
+
+  /// The evaluation of the parent of a state with this evaluation, from the
+  /// perspective of the player to move in the parent. Proven results take one
+  /// more turn to reach from the parent.
+  pub fn backstep(self) -> Self {
+    if self.0 > Self::MAX_HEURISTIC {
+      Self(-self.0 + 1)
+    } else if self.0 < -Self::MAX_HEURISTIC {
+      Self(-self.0 - 1)
+    } else {
+      Self(-self.0)
+    }
+  }
+
+  /// True if this evaluation comes from a proven win or loss rather than a
+  /// heuristic estimate. Proven ties can't be told apart from estimates of 0.
+  pub fn is_proven(self) -> bool {
+    self.0.abs() > Self::MAX_HEURISTIC
+  }
+
+  /// The evaluation as a number, where higher is better for the player to move.
+  pub fn value(self) -> i32 {
+    self.0
+  }
+}
+
+impl Display for Evaluation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if self.0 > Self::MAX_HEURISTIC {
+      write!(f, "win in {}", Self::WIN - self.0)
+    } else if self.0 < -Self::MAX_HEURISTIC {
+      write!(f, "loss in {}", self.0 + Self::WIN)
+    } else {
+      write!(f, "{}", self.0)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use abstract_game::Score;
+
+  use googletest::{gtest, prelude::*};
+
+  use super::Evaluation;
+
+  #[gtest]
+  fn test_proven() {
+    expect_eq!(
+      Evaluation::proven(Score::win(3)).unwrap().to_string(),
+      "win in 3"
+    );
+    expect_eq!(
+      Evaluation::proven(Score::lose(2)).unwrap().to_string(),
+      "loss in 2"
+    );
+    expect_eq!(
+      Evaluation::proven(Score::guaranteed_tie()),
+      Some(Evaluation::heuristic(0))
+    );
+    expect_eq!(Evaluation::proven(Score::tie(5)), None);
+    expect_eq!(Evaluation::proven(Score::NO_INFO), None);
+  }
+
+  #[gtest]
+  fn test_order() {
+    let evaluations = [
+      Evaluation::new(Score::lose(1), 0),
+      Evaluation::new(Score::lose(5), 0),
+      Evaluation::heuristic(i32::MIN),
+      Evaluation::heuristic(-3),
+      Evaluation::new(Score::tie(4), 0),
+      Evaluation::heuristic(7),
+      Evaluation::heuristic(i32::MAX),
+      Evaluation::new(Score::win(5), 0),
+      Evaluation::new(Score::win(1), 0),
+    ];
+    expect_true!(evaluations.is_sorted());
+    expect_true!(evaluations[0].is_proven());
+    expect_false!(evaluations[2].is_proven());
+  }
+
+  #[gtest]
+  fn test_backstep() {
+    expect_eq!(
+      Evaluation::new(Score::win(1), 0).backstep(),
+      Evaluation::new(Score::lose(2), 0)
+    );
+    expect_eq!(
+      Evaluation::new(Score::lose(2), 0).backstep(),
+      Evaluation::new(Score::win(3), 0)
+    );
+    expect_eq!(
+      Evaluation::heuristic(12).backstep(),
+      Evaluation::heuristic(-12)
+    );
+  }
+}
@@ -0,0 +1,424 @@
+use std::{
+  collections::{hash_map::Entry, HashMap},
+  fmt::Display,
+  hash::{BuildHasher, Hash},
+};
+
+use abstract_game::{Game, GameResult};
+use rand::{rng, Rng};
+
+use crate::{
+  global_data::{GlobalData, LookupResult},
+  metrics::Metrics,
+  search_worker::retire_stack,
+  stack::Stack,
+};
+
+/// The top of a search, split into a tree of stacks before any workers start.
+/// The root is claimed and split into child stacks, which are split in turn
+/// until they are `unit_depth` moves below the root. Every state above the
+/// frontier is resolved like any other split stack, once its last child
+/// finishes, and the stacks at the frontier are the work units handed to the
+/// workers.
+///
+/// Finished states are scored directly by their parents, and states which are
+/// already determined in the resolved table are never searched again. A state
+/// which appears more than once at the same depth is only searched by one unit,
+/// which the others wait on. A state which can also be reached in fewer moves
+/// is deferred until every other unit has been queued, by which time the
+/// deeper search of it has often resolved it.
+pub struct Frontier<'a, G, H>
+where
+  G: Game,
+{
+  globals: &'a GlobalData<G, H>,
+  unit_depth: u32,
+  /// The fewest moves from the root that each state within `unit_depth` moves
+  /// of it can be reached in, keyed like the resolved states.
+  min_plies: HashMap<G, u32>,
+  /// Live stacks ready to be queued for the workers.
+  units: Vec<*mut Stack<G>>,
+  /// Live stacks for states which can also be reached in fewer moves, to be
+  /// queued after every unit in `units`.
+  deferred: Vec<*mut Stack<G>>,
+  metrics: Metrics,
+}
+
+impl<'a, G, H> Frontier<'a, G, H>
+where
+  G: Display + Game + Hash + Eq + 'static,
+  G::Move: Display,
+  H: BuildHasher + Clone,
+{
+  pub fn new(globals: &'a GlobalData<G, H>, root: &G, unit_depth: u32) -> Self {
+    Self::expand(globals, root, |ply, _| ply == unit_depth)
+  }
+
+  /// Constructs a frontier with at least `units` states at its bottom level,
+  /// or as many as there are within `max_unit_depth` moves of the root.
+  pub fn with_units(
+    globals: &'a GlobalData<G, H>,
+    root: &G,
+    units: usize,
+    max_unit_depth: u32,
+  ) -> Self {
+    Self::expand(globals, root, |ply, level_size| {
+      ply == max_unit_depth || level_size >= units
+    })
+  }
+
+  /// Finds the fewest moves each state can be reached in, a level at a time,
+  /// until `done` is called with the number of moves from the root and the
+  /// number of states first reached in that many moves, and returns true. The
+  /// last level is the frontier.
+  fn expand(
+    globals: &'a GlobalData<G, H>,
+    root: &G,
+    mut done: impl FnMut(u32, usize) -> bool,
+  ) -> Self {
+    let canonicalizer = globals.resolved_states_table().canonicalizer();
+    let mut min_plies = HashMap::from([(canonicalizer.owned_key(root.clone()), 0)]);
+    let mut level = vec![root.clone()];
+    let mut unit_depth = 0;
+    while !level.is_empty() && !done(unit_depth, level.len()) {
+      unit_depth += 1;
+      let mut next_level = Vec::new();
+      for state in level.into_iter() {
+        if state.finished() != GameResult::NotFinished {
+          continue;
+        }
+        for m in state.each_move() {
+          let child = state.with_move(m);
+          if let Entry::Vacant(entry) = min_plies.entry(canonicalizer.owned_key(child.clone())) {
+            entry.insert(unit_depth);
+            next_level.push(child);
+          }
+        }
+      }
+      level = next_level;
+    }
+
+    Self {
+      globals,
+      unit_depth,
+      min_plies,
+      units: Vec::new(),
+      deferred: Vec::new(),
+      metrics: Metrics::new(),
+    }
+  }
+
+  /// Splits the live stack `stack_ptr`, whose bottom state is `ply` moves from
+  /// the root, into the frontier.
+  pub fn split(&mut self, stack_ptr: *mut Stack<G>, ply: u32) {
+    let globals = self.globals;
+    // Stacks revived while building the frontier are rare, and can go anywhere.
+    let queue = globals.queue(0);
+    let stack = unsafe { &mut *stack_ptr };
+    let game = stack.bottom_frame().unwrap().game();
+    // Split children are never finished, so only the root can be, which the
+    // worker that takes it will score.
+    if game.finished() != GameResult::NotFinished {
+      self.units.push(stack_ptr);
+      return;
+    }
+    let key = globals.resolved_states_table().canonicalizer().key(game);
+    if self
+      .min_plies
+      .get(key.as_ref())
+      .is_some_and(|&min_ply| min_ply < ply)
+    {
+      self.deferred.push(stack_ptr);
+      return;
+    }
+
+    self.metrics.nodes += 1;
+    match globals.get_or_queue(stack_ptr, &mut self.metrics) {
+      LookupResult::Found { score, evaluation } => {
+        stack.pop_with_score(score, evaluation);
+        retire_stack(globals, stack_ptr, queue, &mut self.metrics);
+      }
+      // Whoever claimed the state will revive this stack once it resolves it.
+      LookupResult::Queued => {}
+      // The state is already claimed, so start exploring it here. The worker
+      // that takes the unit picks up from the first child.
+      LookupResult::NotFound if ply == self.unit_depth || stack.bottom_depth() <= 1 => {
+        globals.explore_next_state(stack_ptr, queue, &mut self.metrics);
+        self.place(stack_ptr);
+      }
+      LookupResult::NotFound => {
+        if globals.split_with(stack_ptr, |child_ptr| self.split(child_ptr, ply + 1)) {
+          // Every child was finished or already resolved.
+          globals.revive_split(stack_ptr, queue, &mut self.metrics);
+          self.place(stack_ptr);
+        }
+      }
+    }
+  }
+
+  /// Adds a live stack to the units if it has frames left to explore, and
+  /// otherwise retires it.
+  fn place(&mut self, stack_ptr: *mut Stack<G>) {
+    if unsafe { &*stack_ptr }.bottom_frame().is_some() {
+      self.units.push(stack_ptr);
+    } else {
+      retire_stack(
+        self.globals,
+        stack_ptr,
+        self.globals.queue(0),
+        &mut self.metrics,
+      );
+    }
+  }
+
+  /// Pushes every unit onto the queue of the worker chosen by `pick_queue`,
+  /// returning the metrics from building the frontier.
+  pub fn queue(self, pick_queue: &mut dyn FnMut() -> u32) -> Metrics {
+    for stack_ptr in self.units.into_iter().chain(self.deferred) {
+      self
+        .globals
+        .requeue(stack_ptr, self.globals.queue(pick_queue()));
+    }
+    self.metrics
+  }
+
+  /// Estimates the size of each unit from `playouts` random playouts, then
+  /// pushes the units onto the worker queues largest first, each onto the
+  /// queue with the least estimated work so far. Returns the metrics from
+  /// building the frontier.
+  pub fn queue_balanced(self, num_queues: u32, playouts: u32) -> Metrics {
+    let costs = self.unit_costs(playouts, &mut rng());
+    let mut loads = vec![0.; num_queues as usize];
+    for (unit_idx, queue_idx) in balance(&costs, &mut loads) {
+      self
+        .globals
+        .requeue(self.units[unit_idx], self.globals.queue(queue_idx));
+    }
+    // Deferred units have usually been resolved by the time they are taken,
+    // so they cost next to nothing, and go wherever there is the least work.
+    for &stack_ptr in self.deferred.iter() {
+      let (queue_idx, _) = least_loaded(&loads);
+      loads[queue_idx as usize] += 1.;
+      self
+        .globals
+        .requeue(stack_ptr, self.globals.queue(queue_idx));
+    }
+    self.metrics
+  }
+
+  /// Estimates the size of each unit from `playouts` random playouts. Units
+  /// may have already started exploring their first child, so each estimate
+  /// starts from the state the unit was made for, in its first frame.
+  fn unit_costs(&self, playouts: u32, rng: &mut impl Rng) -> Vec<f64> {
+    self
+      .units
+      .iter()
+      .map(|&stack_ptr| {
+        let stack = unsafe { &*stack_ptr };
+        estimate_size(stack.frame(0).game(), stack.root_depth(), playouts, rng)
+      })
+      .collect()
+  }
+}
+
+/// Estimates the number of states in the tree of `game` searched to `depth`,
+/// averaging Knuth's estimate over `playouts` random playouts: each playout
+/// assumes every state at a given depth has as many moves as the one it
+/// passes through.
+pub fn estimate_size<G: Game>(game: &G, depth: u32, playouts: u32, rng: &mut impl Rng) -> f64 {
+  let total: f64 = (0..playouts)
+    .map(|_| {
+      let mut game = game.clone();
+      let mut size = 1.;
+      let mut level_size = 1.;
+      for _ in 0..depth {
+        if game.finished() != GameResult::NotFinished {
+          break;
+        }
+        let moves: Vec<_> = game.each_move().collect();
+        if moves.is_empty() {
+          break;
+        }
+        level_size *= moves.len() as f64;
+        size += level_size;
+        game.make_move(moves[rng.random_range(0..moves.len())]);
+      }
+      size
+    })
+    .sum();
+  total / playouts.max(1) as f64
+}
+
+/// Returns the index of the queue with the least load, and its load.
+fn least_loaded(loads: &[f64]) -> (u32, f64) {
+  loads
+    .iter()
+    .copied()
+    .enumerate()
+    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    .map(|(queue_idx, load)| (queue_idx as u32, load))
+    .unwrap()
+}
+
+/// Assigns units with the given costs to queues, largest first, each to the
+/// queue with the least load so far, adding the costs to `loads`. Returns the
+/// index of each unit and its queue, in the order the units should be queued.
+fn balance(costs: &[f64], loads: &mut [f64]) -> Vec<(usize, u32)> {
+  let mut order: Vec<_> = (0..costs.len()).collect();
+  order.sort_by(|&a, &b| costs[b].total_cmp(&costs[a]));
+  order
+    .into_iter()
+    .map(|unit_idx| {
+      let (queue_idx, _) = least_loaded(loads);
+      loads[queue_idx as usize] += costs[unit_idx];
+      (unit_idx, queue_idx)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::hash_map::RandomState, sync::Arc};
+
+  use rand::{rng, rngs::StdRng, SeedableRng};
+
+  use crate::{
+    global_data::{GlobalData, StackCounts},
+    search_worker::{start_worker, WorkerData},
+    stack::Stack,
+    test::{nim::Nim, tic_tac_toe::Ttt},
+  };
+
+  use super::{balance, estimate_size, Frontier};
+
+  const STICKS: u32 = 10;
+  const DEPTH: u32 = STICKS + 1;
+
+  fn split_root(globals: &GlobalData<Nim, RandomState>) -> Frontier<'_, Nim, RandomState> {
+    let stack_ptr = Box::into_raw(Box::new(Stack::make_root(Nim::new(STICKS), DEPTH)));
+    globals.new_live_stack();
+    let mut frontier = Frontier::new(globals, &Nim::new(STICKS), 3);
+    frontier.split(stack_ptr, 0);
+    frontier
+  }
+
+  fn search(globals: GlobalData<Nim, RandomState>) -> GlobalData<Nim, RandomState> {
+    let globals = Arc::new(globals);
+    start_worker(WorkerData::new(0, globals.clone()));
+    let globals = Arc::into_inner(globals).unwrap();
+    assert!(globals.finished());
+    assert_eq!(
+      globals.resolved_states_table().get(&Nim::new(STICKS)),
+      Some(Nim::new(STICKS).expected_score())
+    );
+    globals
+  }
+
+  #[test]
+  fn test_transpositions() {
+    let globals = GlobalData::new(DEPTH, 1);
+    let frontier = split_root(&globals);
+
+    // 5 and 4 sticks are the only states first reached 3 moves from the root,
+    // and the second time 5 sticks and 7 sticks are reached at the same depth,
+    // they wait on the first. 8 and 6 sticks can also be reached a move sooner,
+    // so searching them from the deeper ply is deferred.
+    assert_eq!(frontier.units.len(), 2);
+    assert_eq!(frontier.deferred.len(), 2);
+    assert_eq!(globals.stack_counts().suspended, 2);
+
+    frontier.queue(&mut || 0);
+    search(globals);
+  }
+
+  #[test]
+  fn test_skips_resolved_states() {
+    let globals = GlobalData::new(DEPTH, 1);
+    split_root(&globals).queue(&mut || 0);
+    let mut globals = search(globals);
+
+    // The root is already resolved, so there is nothing left to search.
+    globals.reset(DEPTH);
+    let frontier = split_root(&globals);
+    assert!(frontier.units.is_empty());
+    assert!(frontier.deferred.is_empty());
+    assert_eq!(frontier.metrics.hits, 1);
+    assert!(globals.finished());
+    assert_eq!(globals.stack_counts(), StackCounts::default());
+  }
+
+  #[test]
+  fn test_unit_costs() {
+    /// The number of states in the tree of `Nim::new(sticks)`.
+    fn tree_size(sticks: u32) -> f64 {
+      1. + (1..=sticks.min(2))
+        .map(|take| tree_size(sticks - take))
+        .sum::<f64>()
+    }
+
+    let globals = GlobalData::new(DEPTH, 1);
+    let frontier = split_root(&globals);
+
+    // The units for 5 and 4 sticks branch differently, and have already
+    // started exploring their first child, whose tree is smaller than theirs.
+    let costs = frontier.unit_costs(1000, &mut StdRng::seed_from_u64(0x5ea1c0de));
+    for (&stack_ptr, cost) in frontier.units.iter().zip(costs) {
+      let game = unsafe { &*stack_ptr }.frame(0).game();
+      let sticks = (0..=STICKS)
+        .find(|&sticks| game == &Nim::new(sticks))
+        .unwrap();
+      let expected = tree_size(sticks);
+      assert!(
+        (cost - expected).abs() < 0.1 * expected,
+        "{cost} vs {expected}"
+      );
+    }
+
+    frontier.queue(&mut || 0);
+    search(globals);
+  }
+
+  #[test]
+  fn test_with_units() {
+    let globals = GlobalData::<Ttt, _>::new(9, 1);
+    assert_eq!(
+      Frontier::with_units(&globals, &Ttt::new(), 1, 8).unit_depth,
+      0
+    );
+    assert_eq!(
+      Frontier::with_units(&globals, &Ttt::new(), 9, 8).unit_depth,
+      1
+    );
+    assert_eq!(
+      Frontier::with_units(&globals, &Ttt::new(), 10, 8).unit_depth,
+      2
+    );
+    assert_eq!(
+      Frontier::with_units(&globals, &Ttt::new(), 10, 1).unit_depth,
+      1
+    );
+  }
+
+  #[test]
+  fn test_estimate_size() {
+    // Every tic-tac-toe state in the first few moves has one fewer move than
+    // its parent, so every playout gives the exact size.
+    let mut rng = rng();
+    assert_eq!(estimate_size(&Ttt::new(), 0, 4, &mut rng), 1.);
+    assert_eq!(
+      estimate_size(&Ttt::new(), 3, 4, &mut rng),
+      1. + 9. + 72. + 504.
+    );
+    assert_eq!(estimate_size(&Nim::new(0), 3, 4, &mut rng), 1.);
+  }
+
+  #[test]
+  fn test_balance() {
+    let mut loads = vec![0.; 2];
+    assert_eq!(
+      balance(&[1., 5., 3., 3., 4.], &mut loads),
+      vec![(1, 0), (4, 1), (2, 1), (3, 0), (0, 1)]
+    );
+    assert_eq!(loads, vec![8., 8.]);
+  }
+}
@@ -2,13 +2,29 @@ use std::{
   collections::hash_map::RandomState,
   fmt::Display,
   hash::{BuildHasher, Hash},
+  sync::{
+    atomic::{self, AtomicBool, AtomicU32, AtomicU64, Ordering},
+    Arc, Condvar, Mutex,
+  },
+  time::Instant,
 };
 
 use abstract_game::{Game, GameResult, Score};
 use crossbeam_queue::SegQueue;
 use dashmap::{mapref::entry::Entry, DashMap};
 
-use crate::{metrics::Metrics, null_lock::NullLock, stack::Stack, table::Table};
+use crate::{
+  cooperate::CancelHandle,
+  database::Database,
+  evaluate::Evaluation,
+  metrics::Metrics,
+  move_order::MoveOrderer,
+  null_lock::NullLock,
+  principal_variation::ScoreTable,
+  stack::{Stack, StackState, StackType},
+  table::Table,
+  transparent_iterator::TransparentIterator,
+};
 
 struct PendingFrame<G>
 where
@@ -26,8 +42,67 @@ where
   frame_idx: u32,
 }
 
+/// A snapshot of the number of outstanding stacks in each state. Every stack
+/// that has been allocated and not yet freed is counted in exactly one of these.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StackCounts {
+  /// Stacks sitting in some worker's queue.
+  pub queued: u64,
+  /// Stacks currently being worked on by a worker.
+  pub live: u64,
+  /// Stacks waiting on the result of a pending state.
+  pub suspended: u64,
+  /// Stacks waiting on the completion of their split children.
+  pub split: u64,
+}
+
+impl StackCounts {
+  pub fn total(&self) -> u64 {
+    self.queued + self.live + self.suspended + self.split
+  }
+}
+
+/// The live counters behind `StackCounts`. These are only used for accounting,
+/// since reading all four is not atomic. Termination is decided by
+/// `GlobalData::outstanding_stacks` instead.
+#[derive(Default)]
+struct StackAccounting {
+  queued: AtomicU64,
+  live: AtomicU64,
+  suspended: AtomicU64,
+  split: AtomicU64,
+}
+
+impl StackAccounting {
+  /// Moves one stack from the `from` count to the `to` count.
+  fn transition(from: &AtomicU64, to: &AtomicU64) {
+    to.fetch_add(1, Ordering::SeqCst);
+    from.fetch_sub(1, Ordering::SeqCst);
+  }
+
+  fn snapshot(&self) -> StackCounts {
+    StackCounts {
+      queued: self.queued.load(Ordering::Relaxed),
+      live: self.live.load(Ordering::Relaxed),
+      suspended: self.suspended.load(Ordering::Relaxed),
+      split: self.split.load(Ordering::Relaxed),
+    }
+  }
+}
+
+/// Limits on how much work a search may do before it is abandoned.
+#[derive(Default)]
+struct SearchBudget {
+  deadline: Option<Instant>,
+  node_limit: Option<u64>,
+  cancel_handle: CancelHandle,
+}
+
 pub enum LookupResult {
-  Found { score: Score },
+  Found {
+    score: Score,
+    evaluation: Evaluation,
+  },
   NotFound,
   Queued,
 }
@@ -46,6 +121,50 @@ where
   /// degree. They may need to be recomputed to a greater depth, but the
   /// information in this table will only ever accumulate over time.
   resolved_states: Table<G, H>,
+  /// A read-only database of precomputed scores, consulted before
+  /// `resolved_states`.
+  database: Option<Arc<Database<G>>>,
+  /// Orders the moves of every state claimed by a worker.
+  move_orderer: Option<Arc<dyn MoveOrderer<G> + Send + Sync>>,
+  /// If set, checks whether the player to move can win immediately, so states
+  /// where they can are resolved without exploring their children.
+  immediate_win_check: Option<fn(&G) -> bool>,
+  /// If set, searches evaluate the states at their depth horizon with this
+  /// heuristic, and propagate the evaluations alongside the scores. The
+  /// evaluation of every state committed while this is set is kept in
+  /// `resolved_states`.
+  evaluate: Option<fn(&G) -> i32>,
+  /// If set, every state is owned by the worker its key hashes to, and stacks
+  /// are routed to the owner of their bottom state before it is looked up, so
+  /// each state is only looked up and claimed by its owner. The tables are
+  /// still shared, since a state is committed and its claim released by
+  /// whichever worker holds its stack once its children are done. Workers
+  /// never steal in this mode, since that would take stacks away from their
+  /// owners.
+  transposition_driven: bool,
+  /// The number of stacks that have been allocated and not yet freed. This
+  /// includes stacks which are queued, being worked on, suspended on a pending
+  /// state, or split. Once this reaches zero, no more work can appear, so the
+  /// workers may exit.
+  outstanding_stacks: AtomicU64,
+  /// A breakdown of `outstanding_stacks` by what each stack is doing.
+  stack_counts: StackAccounting,
+  /// The number of workers currently parked in `park`, waiting for work to
+  /// appear on some queue.
+  parked_workers: AtomicU32,
+  /// Workers with nothing to do park on this condition variable, and are woken
+  /// whenever a stack is pushed onto a queue or the search finishes.
+  idle_lock: Mutex<()>,
+  idle_cvar: Condvar,
+  budget: SearchBudget,
+  /// The number of nodes searched since the budget was last set. Workers add
+  /// to this in batches, so it may lag behind slightly.
+  nodes_searched: AtomicU64,
+  /// Set once the search has exceeded its budget.
+  out_of_budget: AtomicBool,
+  /// Set if a worker panicked, after which the stacks it held are lost and the
+  /// search can never finish.
+  aborted: AtomicBool,
 }
 
 impl<G> GlobalData<G, RandomState>
@@ -61,6 +180,20 @@ where
         .map(|_| DashMap::<G, PendingFrame<G>, RandomState>::new())
         .collect(),
       resolved_states: Table::new(),
+      database: None,
+      move_orderer: None,
+      immediate_win_check: None,
+      evaluate: None,
+      transposition_driven: false,
+      outstanding_stacks: AtomicU64::new(0),
+      stack_counts: StackAccounting::default(),
+      parked_workers: AtomicU32::new(0),
+      idle_lock: Mutex::new(()),
+      idle_cvar: Condvar::new(),
+      budget: SearchBudget::default(),
+      nodes_searched: AtomicU64::new(0),
+      out_of_budget: AtomicBool::new(false),
+      aborted: AtomicBool::new(false),
     }
   }
 }
@@ -71,43 +204,466 @@ where
   G::Move: Display,
   H: BuildHasher + Clone,
 {
-  pub fn with_hasher(search_depth: u32, num_threads: u32, hasher: H) -> Self {
+  /// Constructs globals which commit resolved states to `resolved_states`.
+  pub fn with_table(search_depth: u32, num_threads: u32, resolved_states: Table<G, H>) -> Self {
+    let hasher = resolved_states.hasher().clone();
     Self {
       queues: (0..num_threads).map(|_| SegQueue::new()).collect(),
       pending_states: (0..search_depth)
         .map(|_| DashMap::<G, PendingFrame<G>, H>::with_hasher(hasher.clone()))
         .collect(),
-      resolved_states: Table::with_hasher(hasher),
+      resolved_states,
+      database: None,
+      move_orderer: None,
+      immediate_win_check: None,
+      evaluate: None,
+      transposition_driven: false,
+      outstanding_stacks: AtomicU64::new(0),
+      stack_counts: StackAccounting::default(),
+      parked_workers: AtomicU32::new(0),
+      idle_lock: Mutex::new(()),
+      idle_cvar: Condvar::new(),
+      budget: SearchBudget::default(),
+      nodes_searched: AtomicU64::new(0),
+      out_of_budget: AtomicBool::new(false),
+      aborted: AtomicBool::new(false),
+    }
+  }
+
+  /// Prepares the globals for another search to `search_depth`, keeping all of
+  /// the states resolved so far. This may only be called once the previous
+  /// search has finished.
+  pub fn reset(&mut self, search_depth: u32) {
+    debug_assert!(self.finished());
+    debug_assert!(self.pending_states.iter().all(|pending| pending.is_empty()));
+
+    let hasher = self.resolved_states.hasher().clone();
+    self.pending_states.truncate(search_depth as usize);
+    for pending in self.pending_states.iter_mut() {
+      pending.clear();
+    }
+    self.pending_states.resize_with(search_depth as usize, || {
+      DashMap::with_hasher(hasher.clone())
+    });
+    self.resolved_states.new_generation();
+  }
+
+  /// Makes searches consult `database` for the scores of states before
+  /// searching them.
+  pub fn set_database(&mut self, database: Option<Arc<Database<G>>>) {
+    self.database = database;
+  }
+
+  /// Makes searches explore the moves of each state in the order chosen by
+  /// `move_orderer`.
+  pub fn set_move_orderer(&mut self, move_orderer: Option<Arc<dyn MoveOrderer<G> + Send + Sync>>) {
+    self.move_orderer = move_orderer;
+  }
+
+  /// Makes searches resolve every state which `immediate_win_check` finds an
+  /// immediate win in to a win in one, without exploring its children.
+  pub fn set_immediate_win_check(&mut self, immediate_win_check: Option<fn(&G) -> bool>) {
+    self.immediate_win_check = immediate_win_check;
+  }
+
+  /// True if the player to move in `game` is known to have a winning move.
+  fn has_immediate_win(&self, game: &G) -> bool {
+    self
+      .immediate_win_check
+      .is_some_and(|immediate_win_check| immediate_win_check(game))
+  }
+
+  /// If the player to move in the bottom frame of `stack` can win with their
+  /// next move, commits the win and pops the frame, returning true.
+  pub fn resolve_immediate_win(&self, stack: &mut Stack<G>, metrics: &mut Metrics) -> bool {
+    let game = stack.bottom_frame().unwrap().game();
+    if !self.has_immediate_win(game) {
+      return false;
+    }
+    metrics.immediate_wins += 1;
+    self.commit_game_with_score(game.clone(), Score::win(1));
+    stack.pop_with_score(Score::win(1), Evaluation::new(Score::win(1), 0));
+    true
+  }
+
+  /// Makes searches route each stack to the worker which owns its bottom state
+  /// before looking the state up, rather than sharing every state between all
+  /// of the workers.
+  pub fn set_transposition_driven(&mut self, transposition_driven: bool) {
+    self.transposition_driven = transposition_driven;
+  }
+
+  /// Returns the worker which owns `game` if stacks are routed to the owners of
+  /// their states, and otherwise `None`.
+  pub fn owner(&self, game: &G) -> Option<u32> {
+    if !self.transposition_driven {
+      return None;
+    }
+    let key = self.resolved_states.canonicalizer().key(game);
+    let hash = self.resolved_states.hasher().hash_one(key.as_ref());
+    Some((hash % self.queues.len() as u64) as u32)
+  }
+
+  /// Pushes a live stack onto the queue of `owner`, the worker which owns its
+  /// bottom state.
+  pub fn route(&self, stack_ptr: *mut Stack<G>, owner: u32, metrics: &mut Metrics) {
+    metrics.routes += 1;
+    self.requeue(stack_ptr, self.queue(owner));
+  }
+
+  /// True if worker `thread_idx`, which has just claimed the bottom state of
+  /// `stack`, should split it rather than explore it depth-first. When stacks
+  /// are routed to the owners of their states, this is only worth it if the
+  /// next child belongs to another worker, since exploring it would hand the
+  /// whole stack over anyway. Otherwise, it is worth it if some worker is
+  /// starving.
+  pub fn should_split(&self, stack: &Stack<G>, thread_idx: u32) -> bool {
+    if !self.transposition_driven {
+      return self.starving();
+    }
+    let frame = stack.bottom_frame().unwrap();
+    frame
+      .current_move()
+      .and_then(|m| self.owner(&frame.game().with_move(m)))
+      .is_some_and(|owner| owner != thread_idx)
+  }
+
+  /// Makes searches evaluate the states at their depth horizon with
+  /// `evaluate`, and track the evaluation of every state they commit.
+  pub fn set_evaluate(&mut self, evaluate: Option<fn(&G) -> i32>) {
+    if evaluate.is_some() {
+      self.resolved_states.track_evaluations();
+    }
+    self.evaluate = evaluate;
+  }
+
+  /// True if searches are tracking evaluations.
+  pub fn evaluating(&self) -> bool {
+    self.evaluate.is_some()
+  }
+
+  /// The heuristic evaluation of `game`, without searching it.
+  fn static_evaluation(&self, game: &G) -> Evaluation {
+    Evaluation::heuristic(self.evaluate.map_or(0, |evaluate| evaluate(game)))
+  }
+
+  /// The best known evaluation of `game`, which has `score`. Proven scores
+  /// take precedence, followed by the deepest evaluation committed for `game`,
+  /// falling back to the heuristic evaluation of `game` itself.
+  fn evaluation(&self, game: &G, score: Score) -> Evaluation {
+    Evaluation::proven(score).unwrap_or_else(|| {
+      self
+        .committed_evaluation(game)
+        .unwrap_or_else(|| self.static_evaluation(game))
+    })
+  }
+
+  /// The evaluation of the child of `game` reached by `m`, from the
+  /// perspective of the player to move in `game`.
+  fn child_evaluation(&self, game: &G, m: G::Move) -> Evaluation {
+    let child = game.with_move(m);
+    let score = match child.finished() {
+      GameResult::Win(winner) => {
+        if winner == game.current_player() {
+          Score::win(1)
+        } else {
+          Score::lose(1)
+        }
+      }
+      GameResult::Tie => Score::guaranteed_tie(),
+      GameResult::NotFinished => {
+        let score = self.resolved_states.get(&child).unwrap_or(Score::NO_INFO);
+        return self.evaluation(&child, score).backstep();
+      }
+    };
+    Evaluation::new(score, 0)
+  }
+
+  /// The evaluation of `game`, which has `score`, recomputed from its
+  /// children.
+  fn evaluation_from_children(&self, game: &G, score: Score) -> Evaluation {
+    if !self.evaluating() {
+      return Evaluation::new(score, 0);
+    }
+    game
+      .each_move()
+      .map(|m| self.child_evaluation(game, m))
+      .max()
+      .unwrap_or(Evaluation::new(Score::lose(1), 0))
+  }
+
+  /// The evaluation committed for `game`, if it was committed while searches
+  /// were tracking evaluations.
+  pub fn committed_evaluation(&self, game: &G) -> Option<Evaluation> {
+    if !self.evaluating() {
+      return None;
     }
+    self.resolved_states.get_evaluation(game)
+  }
+
+  /// The move from `game` to the child with the best evaluation, or `None` if
+  /// there are no moves.
+  pub fn best_evaluated_move(&self, game: &G) -> Option<G::Move> {
+    game
+      .each_move()
+      .max_by_key(|&m| self.child_evaluation(game, m))
+  }
+
+  /// The sequence of moves with the best evaluations for both players starting
+  /// from `game`, following the states with committed evaluations, at most
+  /// `depth` moves long.
+  pub fn evaluated_variation(&self, game: &G, depth: u32) -> Vec<G::Move> {
+    let mut moves = Vec::new();
+    let mut game = game.clone();
+    while moves.len() < depth as usize
+      && game.finished() == GameResult::NotFinished
+      && self.committed_evaluation(&game).is_some()
+    {
+      let Some(m) = self.best_evaluated_move(&game) else {
+        break;
+      };
+      moves.push(m);
+      game = game.with_move(m);
+    }
+    moves
+  }
+
+  /// Limits the searches run from now on to finish before `deadline` and to
+  /// search at most `node_limit` nodes between them, and lets `cancel_handle`
+  /// cancel them. Once any of these is exceeded, workers abandon every stack
+  /// they take until the search has finished.
+  pub fn set_budget(
+    &mut self,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    cancel_handle: CancelHandle,
+  ) {
+    debug_assert!(self.finished());
+    self.budget = SearchBudget {
+      deadline,
+      node_limit,
+      cancel_handle,
+    };
+    *self.nodes_searched.get_mut() = 0;
+    *self.out_of_budget.get_mut() = false;
+  }
+
+  /// Adds `nodes` to the count of nodes searched, returning the new count.
+  pub fn record_nodes(&self, nodes: u64) -> u64 {
+    self.nodes_searched.fetch_add(nodes, Ordering::Relaxed) + nodes
+  }
+
+  /// Adds `nodes` to the count of nodes searched, and checks whether the search
+  /// has exceeded its budget. Returns true if the search should be stopped.
+  pub fn charge_nodes(&self, nodes: u64) -> bool {
+    let nodes_searched = self.record_nodes(nodes);
+    if self
+      .budget
+      .node_limit
+      .is_some_and(|node_limit| nodes_searched >= node_limit)
+      || self
+        .budget
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+    {
+      self.out_of_budget.store(true, Ordering::Release);
+    }
+    self.stopped()
+  }
+
+  /// True if the search has been cancelled, has run out of budget, or has been
+  /// aborted. This never changes back to false during a search.
+  pub fn stopped(&self) -> bool {
+    self.out_of_budget.load(Ordering::Acquire)
+      || self.budget.cancel_handle.is_cancelled()
+      || self.aborted()
+  }
+
+  /// Stops the search for good after a worker panicked. The stacks the worker
+  /// held are never freed, so the remaining workers abandon what they can and
+  /// exit without waiting for every stack to be freed. The globals can't be
+  /// searched with again.
+  pub fn abort(&self) {
+    self.aborted.store(true, Ordering::SeqCst);
+    let _guard = self.idle_lock.lock().unwrap();
+    self.idle_cvar.notify_all();
+  }
+
+  /// True if the search was aborted by `abort`.
+  pub fn aborted(&self) -> bool {
+    self.aborted.load(Ordering::Acquire)
   }
 
   pub fn queue(&self, thread_idx: u32) -> &SegQueue<NullLock<*mut Stack<G>>> {
     self.queues.get(thread_idx as usize).unwrap()
   }
 
+  /// Pushes a newly allocated stack onto the queue of worker `thread_idx`,
+  /// accounting for it in the outstanding stack count. The stack must
+  /// eventually be released with `free_stack`.
+  #[cfg(test)]
+  pub fn queue_new_stack(&self, thread_idx: u32, stack_ptr: *mut Stack<G>) {
+    self.outstanding_stacks.fetch_add(1, Ordering::SeqCst);
+    self.stack_counts.queued.fetch_add(1, Ordering::SeqCst);
+    self.push_queued(self.queue(thread_idx), stack_ptr);
+  }
+
+  /// Accounts for a newly allocated stack which the caller keeps working on
+  /// rather than queueing, counting it as live. The stack must eventually be
+  /// released with `free_stack`.
+  pub fn new_live_stack(&self) {
+    self.outstanding_stacks.fetch_add(1, Ordering::SeqCst);
+    self.stack_counts.live.fetch_add(1, Ordering::SeqCst);
+  }
+
+  /// Pushes a stack which has already been counted as queued onto `queue`, and
+  /// wakes any parked workers so they can steal it.
+  fn push_queued(&self, queue: &SegQueue<NullLock<*mut Stack<G>>>, stack_ptr: *mut Stack<G>) {
+    queue.push(unsafe { NullLock::new(stack_ptr) });
+    self.notify_parked();
+  }
+
+  /// Takes the next stack to work on, either from this worker's own queue or,
+  /// if that is empty, by stealing from the queue of some other worker. The
+  /// returned stack is counted as live.
+  pub fn take_work(&self, thread_idx: u32, metrics: &mut Metrics) -> Option<*mut Stack<G>> {
+    let stack_ptr = match self.queue(thread_idx).pop() {
+      Some(stack_ptr) => *stack_ptr,
+      None if self.transposition_driven => return None,
+      None => {
+        let stack_ptr = self.steal(thread_idx)?;
+        metrics.steals += 1;
+        stack_ptr
+      }
+    };
+    StackAccounting::transition(&self.stack_counts.queued, &self.stack_counts.live);
+    Some(stack_ptr)
+  }
+
+  /// Tries to steal a stack from the queue of some other worker, starting with
+  /// the worker immediately after `thread_idx`.
+  fn steal(&self, thread_idx: u32) -> Option<*mut Stack<G>> {
+    let num_threads = self.queues.len();
+    (1..num_threads)
+      .map(|offset| &self.queues[(thread_idx as usize + offset) % num_threads])
+      .find_map(|queue| queue.pop())
+      .map(|stack_ptr| *stack_ptr)
+  }
+
+  /// Frees a live stack that was allocated with `Box::into_raw`. The caller
+  /// must have exclusive access to the stack.
+  pub fn free_stack(&self, stack_ptr: *mut Stack<G>) {
+    unsafe { drop(Box::from_raw(stack_ptr)) };
+    self.stack_counts.live.fetch_sub(1, Ordering::SeqCst);
+    if self.outstanding_stacks.fetch_sub(1, Ordering::SeqCst) == 1 {
+      // This was the last stack, so wake everyone up to exit.
+      self.notify_parked();
+    }
+  }
+
+  /// True once every stack has been freed, meaning there is no more work that
+  /// could ever appear on any queue.
+  pub fn finished(&self) -> bool {
+    self.outstanding_stacks.load(Ordering::SeqCst) == 0
+  }
+
+  /// A snapshot of the number of outstanding stacks in each state.
+  pub fn stack_counts(&self) -> StackCounts {
+    self.stack_counts.snapshot()
+  }
+
+  /// Blocks worker `thread_idx` until some stack may have been pushed onto a
+  /// queue it can take from, or until the search has finished. This may return
+  /// spuriously, so callers should retry `take_work` and check `finished`
+  /// afterwards.
+  pub fn park(&self, thread_idx: u32) {
+    let guard = self.idle_lock.lock().unwrap();
+    // Registering as parked before checking the queued count pairs with
+    // `notify_parked`, which reads the parked count after pushing. Either we
+    // see the pushed stack here, or the pusher sees us and notifies after we
+    // have started waiting (it can't notify before then, since we hold the
+    // lock).
+    self.parked_workers.fetch_add(1, Ordering::SeqCst);
+    if !self.has_work(thread_idx) && !self.finished() && !self.aborted() {
+      drop(self.idle_cvar.wait(guard).unwrap());
+    }
+    self.parked_workers.fetch_sub(1, Ordering::SeqCst);
+  }
+
+  /// True if there is a queued stack that worker `thread_idx` can take.
+  fn has_work(&self, thread_idx: u32) -> bool {
+    if self.transposition_driven {
+      // Pairs with the fence in `notify_parked`, since pushing onto a queue
+      // isn't sequentially consistent like the queued count is.
+      atomic::fence(Ordering::SeqCst);
+      !self.queue(thread_idx).is_empty()
+    } else {
+      self.stack_counts.queued.load(Ordering::SeqCst) != 0
+    }
+  }
+
+  /// True if some worker is parked and there is nothing queued for it to
+  /// steal, in which case live stacks should be split to give it work.
+  pub fn starving(&self) -> bool {
+    self.parked_workers.load(Ordering::Relaxed) != 0
+      && self.stack_counts.queued.load(Ordering::Relaxed) == 0
+  }
+
+  fn notify_parked(&self) {
+    if self.transposition_driven {
+      atomic::fence(Ordering::SeqCst);
+    }
+    if self.parked_workers.load(Ordering::SeqCst) != 0 {
+      let _guard = self.idle_lock.lock().unwrap();
+      self.idle_cvar.notify_all();
+    }
+  }
+
   pub fn resolved_states_table(&self) -> &Table<G, H> {
     &self.resolved_states
   }
 
-  /// Will try to find the bottom frame of the stack in the state tables. If it
-  /// isn't found, or it is found but wasn't searched deep enough, it will
-  /// reserve a spot in `pending_states` by placing the bottom game state of the
-  /// stack.
+  /// Will try to find the bottom frame of the stack in the database and the
+  /// state tables. If it isn't found, or it is found but wasn't searched deep
+  /// enough, it will reserve a spot in `pending_states` by placing the bottom
+  /// game state of the stack.
   pub fn get_or_queue(&self, stack_ptr: *mut Stack<G>, metrics: &mut Metrics) -> LookupResult {
     let stack = unsafe { &mut *stack_ptr };
     let bottom_state = stack.bottom_frame().unwrap();
     let game = bottom_state.game();
+    // Symmetric states are claimed and looked up under the same key.
+    let key = self.resolved_states.canonicalizer().key(game);
+    if let Some(score) = self
+      .database
+      .as_ref()
+      .and_then(|database| database.get(&key))
+    {
+      if score.determined(stack.bottom_depth()) {
+        // Copy the score into the resolved table, since everything which reads
+        // scores back after the search (e.g. split stacks and the root of the
+        // search) only looks there.
+        self.resolved_states.update(key.into_owned(), score);
+        metrics.database_hits += 1;
+        return LookupResult::Found {
+          score,
+          evaluation: self.evaluation(game, score),
+        };
+      }
+    }
     if let Some(score) = self.resolved_states.get(game) {
       if score.determined(stack.bottom_depth()) {
         metrics.hits += 1;
-        return LookupResult::Found { score };
+        return LookupResult::Found {
+          score,
+          evaluation: self.evaluation(game, score),
+        };
       }
     }
 
     // If the state wasn't found in the resolved table, then try to insert it
     // into its respective pending table.
     let depth_idx = stack.bottom_depth() as usize - 1;
-    match self.pending_states[depth_idx].entry(game.clone()) {
+    match self.pending_states[depth_idx].entry(key.into_owned()) {
       Entry::Occupied(entry) => {
         // If there is already a pending computation, then queue ourselves on it.
         let pending_frame = entry.get();
@@ -119,6 +675,7 @@ where
           (*stack_ptr).suspend();
           frame.queue_dependant_unlocked(stack_ptr);
         }
+        StackAccounting::transition(&self.stack_counts.live, &self.stack_counts.suspended);
 
         metrics.queues += 1;
         LookupResult::Queued
@@ -131,6 +688,7 @@ where
 
         // We claimed the pending slot.
         metrics.claims += 1;
+        self.order_moves(stack);
         LookupResult::NotFound
       }
     }
@@ -141,6 +699,19 @@ where
     // deal.
   }
 
+  /// Reorders the moves of the bottom frame of `stack`, which has just been
+  /// claimed, with the move orderer if there is one.
+  fn order_moves(&self, stack: &mut Stack<G>) {
+    let Some(move_orderer) = &self.move_orderer else {
+      return;
+    };
+    let depth = stack.bottom_depth();
+    let frame = stack.bottom_frame_mut().unwrap();
+    let mut moves: Vec<_> = frame.game().each_move().collect();
+    move_orderer.order_moves(frame.game(), depth, Some(&self.resolved_states), &mut moves);
+    frame.set_move_order(moves);
+  }
+
   /// Commits the scores of every complete stack frame, if there are any and
   /// starting from the bottom, and finds the next move that needs to be
   /// explored.
@@ -150,6 +721,7 @@ where
     &self,
     stack_ptr: *mut Stack<G>,
     queue: &SegQueue<NullLock<*mut Stack<G>>>,
+    metrics: &mut Metrics,
   ) {
     let stack = unsafe { &mut *stack_ptr };
 
@@ -162,32 +734,39 @@ where
           // println!("  move {} for\n{}", m, bottom_state.game());
 
           if bottom_depth == 1 {
-            let score = match game.finished() {
+            metrics.nodes += 1;
+            let (score, evaluation) = match game.finished() {
               GameResult::Win(winner) => {
-                if winner == bottom_state.game().current_player() {
+                metrics.terminal_states += 1;
+                let score = if winner == bottom_state.game().current_player() {
                   Score::win(1)
                 } else {
                   Score::lose(1)
-                }
+                };
+                (score, Evaluation::new(score, 0))
+              }
+              GameResult::Tie => {
+                metrics.terminal_states += 1;
+                let score = Score::guaranteed_tie();
+                (score, Evaluation::new(score, 0))
               }
-              GameResult::Tie => Score::guaranteed_tie(),
               GameResult::NotFinished => {
-                Score::tie(1)
-                // TODO: not immediately clear if search imm win is faster.
-                // if game.search_immediate_win().is_some() {
-                //   self.commit_game_with_score(bottom_state.game().clone(), Score::win(1));
-                //   // If this game is a win for the current player, it's a lose for the
-                //   // player of the previous turn.
-                //   Score::lose(2)
-                // } else {
-                //   // Don't commit game, since we have no information on it (tie to
-                //   // depth 1 is not worth committing).
-                //   Score::tie(1)
-                // }
+                if self.has_immediate_win(&game) {
+                  metrics.immediate_wins += 1;
+                  self.commit_game_with_score(game, Score::win(1));
+                  // If this game is a win for the current player, it's a loss
+                  // for the player of the previous turn.
+                  (Score::lose(2), Evaluation::new(Score::lose(2), 0))
+                } else {
+                  // Don't commit game, since we have no information on it (tie
+                  // to depth 1 is not worth committing). Its heuristic
+                  // evaluation is all we know about it.
+                  (Score::tie(1), self.static_evaluation(&game).backstep())
+                }
               }
             };
 
-            stack.update_parent_score_and_advance(score);
+            stack.update_parent_score_and_advance(score, evaluation);
           } else {
             // println!("  move {} for\n{}", m, bottom_state.game());
             let next_state = bottom_state.game().with_move(m);
@@ -196,7 +775,7 @@ where
           }
         }
         None => {
-          self.commit_score(stack, stack_ptr, queue);
+          self.commit_score(stack, stack_ptr, queue, metrics);
           bottom_depth += 1
         }
       }
@@ -211,19 +790,55 @@ where
     stack: &mut Stack<G>,
     stack_ptr: *mut Stack<G>,
     queue: &SegQueue<NullLock<*mut Stack<G>>>,
+    metrics: &mut Metrics,
   ) {
-    let depth_idx = stack.bottom_depth() as usize - 1;
-    let bottom_frame_idx = stack.bottom_frame_idx();
-
+    let depth = stack.bottom_depth();
     let bottom_state = stack.bottom_frame_mut().unwrap();
-    let score = bottom_state.best_score().0.clone();
+    let score = bottom_state.best_score().0;
     let game = bottom_state.game().clone();
     // println!("  Out of moves, committing score {} for\n{}", score, game);
-    self.commit_game_with_score(game.clone(), score);
+    if bottom_state.cut_off() {
+      metrics.cutoffs += 1;
+      if let (Some(move_orderer), (_, Some(best_move))) =
+        (&self.move_orderer, bottom_state.best_score())
+      {
+        move_orderer.record_cutoff(&game, depth, best_move);
+      }
+    }
+    if self.evaluating() {
+      self
+        .resolved_states
+        .update_evaluation(game.clone(), depth, bottom_state.evaluation());
+    }
+    self.commit_game_with_score(game, score);
+    metrics.commits += 1;
+    self.release_claim(stack, stack_ptr, queue, metrics);
+
+    // Pop this state from the stack.
+    stack.pop();
+  }
+
+  /// Removes the bottom frame of the stack from `pending_states`, and re-queues
+  /// every stack that was suspended on it.
+  fn release_claim(
+    &self,
+    stack: &mut Stack<G>,
+    stack_ptr: *mut Stack<G>,
+    queue: &SegQueue<NullLock<*mut Stack<G>>>,
+    metrics: &mut Metrics,
+  ) {
+    let depth_idx = stack.bottom_depth() as usize - 1;
+    let bottom_frame_idx = stack.bottom_frame_idx();
+    let bottom_state = stack.bottom_frame_mut().unwrap();
 
     // Remove the state from the pending states.
     // println!("    removing at {depth_idx}");
-    match self.pending_states[depth_idx].entry(game) {
+    let key = self
+      .resolved_states
+      .canonicalizer()
+      .key(bottom_state.game())
+      .into_owned();
+    match self.pending_states[depth_idx].entry(key) {
       Entry::Occupied(entry) => {
         let pending_frame = entry.remove();
         debug_assert_eq!(*pending_frame.stack, stack_ptr);
@@ -240,14 +855,144 @@ where
     // Re-queue all pending states.
     while let Some(dependant) = unsafe { bottom_state.pop_dependant_unlocked() } {
       unsafe { &mut *dependant }.revive();
-      queue.push(unsafe { NullLock::new(dependant) });
+      StackAccounting::transition(&self.stack_counts.suspended, &self.stack_counts.queued);
+      self.push_queued(queue, dependant);
+      metrics.revivals += 1;
     }
-
-    // Pop this state from the stack.
-    stack.pop();
   }
 
   fn commit_game_with_score(&self, game: G, score: Score) {
     self.resolved_states.update(game, score);
   }
+
+  /// Pushes a live stack back onto `queue`.
+  pub fn requeue(&self, stack_ptr: *mut Stack<G>, queue: &SegQueue<NullLock<*mut Stack<G>>>) {
+    StackAccounting::transition(&self.stack_counts.live, &self.stack_counts.queued);
+    self.push_queued(queue, stack_ptr);
+  }
+
+  /// Splits the bottom frame of a live stack, whose bottom state must already
+  /// be claimed in `pending_states`, into a child stack for each of its moves.
+  /// The children are pushed onto `queue` for idle workers to steal, or onto
+  /// the queues of their owners if stacks are routed to the owners of their
+  /// states.
+  ///
+  /// Returns true if every child had already finished by the time they were
+  /// all handed out (or there were no children to begin with), in which case
+  /// the caller still owns the stack and must call `revive_split` on it.
+  /// Otherwise, the stack may no longer be accessed by the caller.
+  pub fn split(&self, stack_ptr: *mut Stack<G>, queue: &SegQueue<NullLock<*mut Stack<G>>>) -> bool {
+    self.split_with(stack_ptr, |child_ptr| {
+      let child = unsafe { &*child_ptr }.bottom_frame().unwrap().game();
+      let queue = self.owner(child).map_or(queue, |owner| self.queue(owner));
+      self.requeue(child_ptr, queue)
+    })
+  }
+
+  /// Splits the bottom frame of a live stack like `split`, but hands each child
+  /// to `place_child` as a live stack instead of queueing it. `place_child` is
+  /// responsible for queueing, splitting, or otherwise accounting for it.
+  pub fn split_with(
+    &self,
+    stack_ptr: *mut Stack<G>,
+    mut place_child: impl FnMut(*mut Stack<G>),
+  ) -> bool {
+    StackAccounting::transition(&self.stack_counts.live, &self.stack_counts.split);
+
+    let mut all_children_resolved = false;
+    for child in Stack::split(stack_ptr).chain(TransparentIterator::new(|| {
+      // Release the extra outstanding child held while splitting.
+      all_children_resolved = Stack::resolve_outstanding_child(stack_ptr);
+    })) {
+      self.new_live_stack();
+      place_child(Box::into_raw(Box::new(child)));
+    }
+
+    all_children_resolved
+  }
+
+  /// Revives a split stack after all of its children have finished. The score
+  /// of the split frame is recomputed from the children's entries in the
+  /// resolved table and committed, after which the stack is ready to explore
+  /// its next state like any other live stack. If some child's entry has been
+  /// evicted from the table, the split frame is explored again from its first
+  /// child instead. The caller must have exclusive access to the stack.
+  pub fn revive_split(
+    &self,
+    stack_ptr: *mut Stack<G>,
+    queue: &SegQueue<NullLock<*mut Stack<G>>>,
+    metrics: &mut Metrics,
+  ) {
+    let stack = unsafe { &mut *stack_ptr };
+    stack.revive();
+    StackAccounting::transition(&self.stack_counts.split, &self.stack_counts.live);
+
+    let bottom_depth = stack.bottom_depth();
+    let bottom_frame = stack.bottom_frame_mut().unwrap();
+    match self
+      .resolved_states
+      .score_from_children(bottom_frame.game(), bottom_depth)
+    {
+      Some((score, best_move)) => {
+        let evaluation = self.evaluation_from_children(bottom_frame.game(), score);
+        bottom_frame.resolve(score, best_move, evaluation)
+      }
+      // Most of the children should still be in the table, so searching them
+      // again should be quick.
+      None => bottom_frame.restart(),
+    }
+
+    self.explore_next_state(stack_ptr, queue, metrics);
+  }
+
+  /// Abandons a stack once the search has been stopped, without committing any
+  /// of its frames. Every pending state claimed by the stack is released, and
+  /// stacks suspended on them are re-queued so they can be abandoned in turn.
+  /// If this was the last outstanding child of a split stack, the parent is
+  /// abandoned as well. The caller must have exclusive access to the stack,
+  /// which must either be live with an unclaimed bottom frame, or split.
+  pub fn abandon_stack(
+    &self,
+    mut stack_ptr: *mut Stack<G>,
+    queue: &SegQueue<NullLock<*mut Stack<G>>>,
+    metrics: &mut Metrics,
+  ) {
+    debug_assert!(self.stopped());
+    loop {
+      let stack = unsafe { &mut *stack_ptr };
+      // Every frame but the bottom has been claimed, as has the bottom frame of
+      // a split stack.
+      let mut claimed = match stack.stack_state() {
+        StackState::Split => {
+          stack.revive();
+          StackAccounting::transition(&self.stack_counts.split, &self.stack_counts.live);
+          true
+        }
+        state => {
+          debug_assert_eq!(state, StackState::Live);
+          false
+        }
+      };
+      while stack.bottom_frame().is_some() {
+        if claimed {
+          self.release_claim(stack, stack_ptr, queue, metrics);
+        }
+        stack.pop_abandoned();
+        claimed = true;
+      }
+
+      let parent_ptr = match stack.stack_type() {
+        StackType::Root => None,
+        StackType::Child { parent } => Some(*parent),
+      };
+      self.free_stack(stack_ptr);
+
+      match parent_ptr {
+        Some(parent_ptr) if Stack::resolve_outstanding_child(parent_ptr) => {
+          stack_ptr = parent_ptr;
+        }
+        _ => return,
+      }
+    }
+  }
 }
@@ -0,0 +1,66 @@
+use abstract_game::Game;
+
+/// Games which can find a move that wins on the spot more cheaply than by
+/// making each of their moves and checking whether the game has finished.
+/// Solvers which are told to use this resolve states with an immediate win to
+/// `Score::win(1)` without exploring any of their children.
+pub trait ImmediateWin: Game {
+  /// Returns a move which wins the game for the current player, if there is
+  /// one. Missing a winning move is allowed, and only costs the search the
+  /// time it takes to find it by exploring the state.
+  fn search_immediate_win(&self) -> Option<Self::Move>;
+}
+
+/// Whether the player to move in `game` can win with their next move. Solvers
+/// store this as a function pointer, so they can check for immediate wins
+/// without requiring every game they solve to implement `ImmediateWin`.
+pub(crate) fn has_immediate_win<G: ImmediateWin>(game: &G) -> bool {
+  game.search_immediate_win().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+  use abstract_game::{test_util::deterministic_random_unfinished_state, Game, GameResult};
+  use rand::{rngs::StdRng, Rng, SeedableRng};
+
+  use crate::test::gomoku::{Gomoku, GomokuMove};
+
+  use super::ImmediateWin;
+
+  #[test]
+  fn test_gomoku_immediate_win() {
+    let gomoku = [(0, 0), (0, 1), (1, 0), (1, 1)]
+      .into_iter()
+      .fold(Gomoku::new(4, 4, 3), |gomoku, (x, y)| {
+        gomoku.with_move(GomokuMove::new(x, y))
+      });
+    let m = gomoku.search_immediate_win();
+    assert_eq!(m, Some(GomokuMove::new(2, 0)));
+    assert_eq!(
+      gomoku.with_move(m.unwrap()).finished(),
+      GameResult::Win(gomoku.current_player())
+    );
+  }
+
+  #[test]
+  fn test_gomoku_immediate_win_matches_moves() {
+    let mut rng = StdRng::seed_from_u64(0x1e4d7a60c311);
+    for _ in 0..500 {
+      let mut gomoku = Gomoku::new(5, 5, 4);
+      let n_moves = rng.random_range(0..20);
+      deterministic_random_unfinished_state(&mut gomoku, n_moves, &mut rng).unwrap();
+
+      let winning_move = gomoku
+        .each_move()
+        .find(|&m| gomoku.with_move(m).finished() == GameResult::Win(gomoku.current_player()));
+      match gomoku.search_immediate_win() {
+        Some(m) => assert_eq!(
+          gomoku.with_move(m).finished(),
+          GameResult::Win(gomoku.current_player()),
+          "{m} for\n{gomoku}"
+        ),
+        None => assert_eq!(winning_move, None, "for\n{gomoku}"),
+      }
+    }
+  }
+}
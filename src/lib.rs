@@ -1,12 +1,22 @@
+mod bounded_table;
+pub mod canonicalize;
 pub mod cooperate;
+pub mod database;
+pub mod evaluate;
+mod frontier;
 mod global_data;
+pub mod immediate_win;
 pub mod metrics;
+pub mod move_order;
 mod null_lock;
 pub mod passthrough_hasher;
+pub mod persist;
+pub mod principal_variation;
 mod search_worker;
 mod stack;
-mod table;
+pub mod table;
 mod transparent_iterator;
+pub mod zobrist;
 
 pub mod solvers;
 #[cfg(test)]
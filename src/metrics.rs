@@ -1,8 +1,36 @@
-#[derive(Clone, Debug, Default)]
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Metrics {
   pub hits: u64,
+  /// The number of game states whose score was found in the database.
+  pub database_hits: u64,
   pub queues: u64,
   pub claims: u64,
+  /// The number of game states visited, including states which were found in
+  /// the table and states at the search horizon.
+  pub nodes: u64,
+  /// The number of visited game states which had finished.
+  pub terminal_states: u64,
+  /// The number of unfinished game states resolved to a win without exploring
+  /// their children, since the player to move had an immediate win.
+  pub immediate_wins: u64,
+  /// The number of scores committed to the resolved table.
+  pub commits: u64,
+  /// The number of committed states whose remaining moves were cut off by
+  /// alpha-beta pruning.
+  pub cutoffs: u64,
+  /// The number of suspended stacks revived after the state they were waiting
+  /// on was resolved or abandoned.
+  pub revivals: u64,
+  /// The number of stacks taken from another worker's queue.
+  pub steals: u64,
+  /// The number of stacks pushed onto the queue of the worker which owns their
+  /// bottom state, in transposition-driven mode.
+  pub routes: u64,
+  /// The time spent in `start_worker`. When summed, this is the total time
+  /// across all threads rather than the time the search took.
+  pub wall_time: Duration,
 }
 
 impl Metrics {
@@ -17,8 +45,18 @@ impl std::ops::Add for Metrics {
   fn add(self, rhs: Self) -> Self::Output {
     Self {
       hits: self.hits + rhs.hits,
+      database_hits: self.database_hits + rhs.database_hits,
       queues: self.queues + rhs.queues,
       claims: self.claims + rhs.claims,
+      nodes: self.nodes + rhs.nodes,
+      terminal_states: self.terminal_states + rhs.terminal_states,
+      immediate_wins: self.immediate_wins + rhs.immediate_wins,
+      commits: self.commits + rhs.commits,
+      cutoffs: self.cutoffs + rhs.cutoffs,
+      revivals: self.revivals + rhs.revivals,
+      steals: self.steals + rhs.steals,
+      routes: self.routes + rhs.routes,
+      wall_time: self.wall_time + rhs.wall_time,
     }
   }
 }
@@ -28,3 +66,9 @@ impl std::ops::AddAssign for Metrics {
     *self = self.clone() + rhs;
   }
 }
+
+impl std::iter::Sum for Metrics {
+  fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+    iter.fold(Self::new(), |total, metrics| total + metrics)
+  }
+}
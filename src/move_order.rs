@@ -0,0 +1,326 @@
+use std::{cmp::Reverse, collections::HashMap, hash::Hash, sync::Mutex};
+
+use abstract_game::{Game, GameResult, Score};
+
+use crate::principal_variation::ScoreTable;
+
+/// Chooses the order the moves of a state are searched in. Alpha-beta pruning
+/// cuts off the remaining moves of a state as soon as one of them is good
+/// enough, so the sooner the best move is searched, the more is pruned.
+///
+/// Orderers are shared between every worker of the cooperative solver, so any
+/// state they keep must use interior mutability. Orderers must sort stably, so
+/// that they can be combined: the pair `(A, B)` orders moves by `A`, breaking
+/// ties with `B`.
+pub trait MoveOrderer<G: Game> {
+  /// Sorts `moves`, which are all of the moves of `game`, into the order they
+  /// should be searched in when searching `game` to `depth`. `table` holds the
+  /// scores the solver has found so far, if it keeps any.
+  fn order_moves(
+    &self,
+    game: &G,
+    depth: u32,
+    table: Option<&dyn ScoreTable<G>>,
+    moves: &mut [G::Move],
+  );
+
+  /// Called when searching `m` from `game` to `depth` cut off the rest of the
+  /// moves of `game`.
+  fn record_cutoff(&self, _game: &G, _depth: u32, _m: G::Move) {}
+}
+
+/// Searches moves in the order they are generated.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoOrdering;
+
+impl<G: Game> MoveOrderer<G> for NoOrdering {
+  fn order_moves(&self, _: &G, _: u32, _: Option<&dyn ScoreTable<G>>, _: &mut [G::Move]) {}
+}
+
+/// Searches moves that finish the game, or lead to states with the best known
+/// scores in the table, first. Moves with nothing known about them are searched
+/// after those known to tie and before those known to lose.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TableScoreOrdering;
+
+impl<G: Game> MoveOrderer<G> for TableScoreOrdering {
+  fn order_moves(
+    &self,
+    game: &G,
+    _depth: u32,
+    table: Option<&dyn ScoreTable<G>>,
+    moves: &mut [G::Move],
+  ) {
+    moves.sort_by_cached_key(|&m| Reverse(known_score(game, m, table)));
+  }
+}
+
+/// Returns what is known about the score of making `m` from `game`, from the
+/// perspective of the player making it.
+fn known_score<G: Game>(game: &G, m: G::Move, table: Option<&dyn ScoreTable<G>>) -> Score {
+  let next_state = game.with_move(m);
+  match next_state.finished() {
+    GameResult::Win(winner) => {
+      if winner == game.current_player() {
+        Score::win(1)
+      } else {
+        Score::lose(1)
+      }
+    }
+    GameResult::Tie => Score::guaranteed_tie(),
+    GameResult::NotFinished => table
+      .and_then(|table| table.lookup_score(&next_state))
+      .map_or(Score::NO_INFO, |score| score.backstep()),
+  }
+}
+
+/// The number of killer moves remembered for each depth.
+const KILLERS_PER_DEPTH: usize = 2;
+
+/// Searches the moves which most recently caused cutoffs at the same depth
+/// first. Sibling states tend to be refuted by the same move, so a move which
+/// cut off the search of one of them is likely to cut off the others.
+pub struct KillerMoves<M> {
+  /// The killer moves at each depth, most recent first.
+  killers: Mutex<Vec<[Option<M>; KILLERS_PER_DEPTH]>>,
+}
+
+impl<M> KillerMoves<M> {
+  pub fn new() -> Self {
+    Self {
+      killers: Mutex::new(Vec::new()),
+    }
+  }
+}
+
+impl<M> Default for KillerMoves<M> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<G> MoveOrderer<G> for KillerMoves<G::Move>
+where
+  G: Game,
+  G::Move: PartialEq,
+{
+  fn order_moves(
+    &self,
+    _game: &G,
+    depth: u32,
+    _table: Option<&dyn ScoreTable<G>>,
+    moves: &mut [G::Move],
+  ) {
+    let killers = self.killers.lock().unwrap();
+    if let Some(killers) = killers.get(depth as usize) {
+      moves.sort_by_key(|m| {
+        killers
+          .iter()
+          .position(|killer| killer.as_ref() == Some(m))
+          .unwrap_or(KILLERS_PER_DEPTH)
+      });
+    }
+  }
+
+  fn record_cutoff(&self, _game: &G, depth: u32, m: G::Move) {
+    let mut killers = self.killers.lock().unwrap();
+    let depth = depth as usize;
+    if killers.len() <= depth {
+      killers.resize_with(depth + 1, Default::default);
+    }
+    let killers = &mut killers[depth];
+    if killers[0] != Some(m) {
+      killers.rotate_right(1);
+      killers[0] = Some(m);
+    }
+  }
+}
+
+/// Searches the moves which have caused the most cutoffs anywhere in the search
+/// first, weighting cutoffs deeper in the search more heavily since they prune
+/// larger subtrees.
+pub struct HistoryHeuristic<M> {
+  history: Mutex<HashMap<M, u64>>,
+}
+
+impl<M> HistoryHeuristic<M> {
+  pub fn new() -> Self {
+    Self {
+      history: Mutex::new(HashMap::new()),
+    }
+  }
+}
+
+impl<M> Default for HistoryHeuristic<M> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<G> MoveOrderer<G> for HistoryHeuristic<G::Move>
+where
+  G: Game,
+  G::Move: Hash + Eq,
+{
+  fn order_moves(
+    &self,
+    _game: &G,
+    _depth: u32,
+    _table: Option<&dyn ScoreTable<G>>,
+    moves: &mut [G::Move],
+  ) {
+    let history = self.history.lock().unwrap();
+    moves.sort_by_cached_key(|m| Reverse(history.get(m).copied().unwrap_or(0)));
+  }
+
+  fn record_cutoff(&self, _game: &G, depth: u32, m: G::Move) {
+    let depth = depth as u64;
+    *self.history.lock().unwrap().entry(m).or_default() += depth * depth;
+  }
+}
+
+/// Games which can guess how promising each of their moves is without
+/// searching them.
+pub trait MoveHeuristic: Game {
+  /// Returns how promising `m` is for the current player. Moves with higher
+  /// priorities are searched first.
+  fn move_priority(&self, m: Self::Move) -> i64;
+}
+
+/// Searches moves in order of the game's own `MoveHeuristic`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeuristicOrdering;
+
+impl<G: MoveHeuristic> MoveOrderer<G> for HeuristicOrdering {
+  fn order_moves(
+    &self,
+    game: &G,
+    _depth: u32,
+    _table: Option<&dyn ScoreTable<G>>,
+    moves: &mut [G::Move],
+  ) {
+    moves.sort_by_cached_key(|&m| Reverse(game.move_priority(m)));
+  }
+}
+
+impl<G, A, B> MoveOrderer<G> for (A, B)
+where
+  G: Game,
+  A: MoveOrderer<G>,
+  B: MoveOrderer<G>,
+{
+  fn order_moves(
+    &self,
+    game: &G,
+    depth: u32,
+    table: Option<&dyn ScoreTable<G>>,
+    moves: &mut [G::Move],
+  ) {
+    // Both orderings are stable, so ordering by `A` last keeps moves `A`
+    // considers equal in the order `B` put them in.
+    self.1.order_moves(game, depth, table, moves);
+    self.0.order_moves(game, depth, table, moves);
+  }
+
+  fn record_cutoff(&self, game: &G, depth: u32, m: G::Move) {
+    self.0.record_cutoff(game, depth, m);
+    self.1.record_cutoff(game, depth, m);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use abstract_game::{test_games::Nim, Game, Score};
+
+  use googletest::{gtest, prelude::*};
+
+  use crate::{
+    principal_variation::ScoreTable,
+    test::gomoku::{Gomoku, GomokuMove},
+  };
+
+  use super::{
+    HeuristicOrdering, HistoryHeuristic, KillerMoves, MoveOrderer, NoOrdering, TableScoreOrdering,
+  };
+
+  fn ordered<G: Game>(orderer: &impl MoveOrderer<G>, game: &G, depth: u32) -> Vec<G::Move> {
+    let mut moves: Vec<_> = game.each_move().collect();
+    orderer.order_moves(game, depth, None, &mut moves);
+    moves
+  }
+
+  #[gtest]
+  fn test_no_ordering() {
+    expect_that!(
+      ordered(&NoOrdering, &Nim::new(5), 1),
+      elements_are![eq(&1), eq(&2)]
+    );
+  }
+
+  #[gtest]
+  fn test_table_score_ordering() {
+    let nim = Nim::new(5);
+    // Taking 1 stick leaves 4, which is a win for the other player.
+    let table = HashMap::from([(nim.with_move(1), Score::win(3))]);
+    let mut moves: Vec<_> = nim.each_move().collect();
+    TableScoreOrdering.order_moves(&nim, 3, Some(&table as &dyn ScoreTable<_>), &mut moves);
+    expect_that!(moves, elements_are![eq(&2), eq(&1)]);
+  }
+
+  #[gtest]
+  fn test_table_score_ordering_winning_move_first() {
+    // Taking the last stick wins, even with nothing in the table.
+    expect_that!(
+      ordered(&TableScoreOrdering, &Nim::new(2), 1),
+      elements_are![eq(&2), eq(&1)]
+    );
+  }
+
+  #[gtest]
+  fn test_killer_moves() {
+    let nim = Nim::new(5);
+    let killers = KillerMoves::new();
+    killers.record_cutoff(&nim, 3, 2);
+    expect_that!(ordered(&killers, &nim, 3), elements_are![eq(&2), eq(&1)]);
+    // Killers are only used at the depth they were recorded at.
+    expect_that!(ordered(&killers, &nim, 2), elements_are![eq(&1), eq(&2)]);
+  }
+
+  #[gtest]
+  fn test_history_heuristic() {
+    let nim = Nim::new(5);
+    let history = HistoryHeuristic::new();
+    history.record_cutoff(&nim, 2, 1);
+    history.record_cutoff(&nim, 3, 2);
+    expect_that!(ordered(&history, &nim, 1), elements_are![eq(&2), eq(&1)]);
+    history.record_cutoff(&nim, 2, 1);
+    history.record_cutoff(&nim, 2, 1);
+    expect_that!(ordered(&history, &nim, 1), elements_are![eq(&1), eq(&2)]);
+  }
+
+  #[gtest]
+  fn test_combined_ordering() {
+    let nim = Nim::new(5);
+    let killers = KillerMoves::new();
+    killers.record_cutoff(&nim, 3, 2);
+    let orderer = (NoOrdering, killers);
+    expect_that!(ordered(&orderer, &nim, 3), elements_are![eq(&2), eq(&1)]);
+  }
+
+  #[gtest]
+  fn test_gomoku_heuristic() {
+    let gomoku = Gomoku::new(4, 4, 3).with_move(GomokuMove::new(0, 0));
+    let moves = ordered(&HeuristicOrdering, &gomoku, 1);
+    // The neighbors of the only piece are searched first.
+    expect_that!(
+      moves[..3],
+      unordered_elements_are![
+        eq(&GomokuMove::new(1, 0)),
+        eq(&GomokuMove::new(0, 1)),
+        eq(&GomokuMove::new(1, 1))
+      ]
+    );
+  }
+}
@@ -1,3 +1,6 @@
+/// A hasher which uses the single `u64` written to it as the hash. This is
+/// meant for keys which are already uniformly distributed hashes, like the
+/// Zobrist hashes of game states (see `zobrist::ZobristHash`).
 pub struct PassThroughHasher {
   state: u64,
 }
@@ -5,7 +8,16 @@ pub struct PassThroughHasher {
 impl std::hash::Hasher for PassThroughHasher {
   fn write(&mut self, bytes: &[u8]) {
     debug_assert!(bytes.len() == 8 && self.state == 0);
-    self.state = unsafe { *(bytes.as_ptr() as *const u64) };
+    self.state = u64::from_ne_bytes(
+      bytes
+        .try_into()
+        .expect("PassThroughHasher can only hash a single u64."),
+    );
+  }
+
+  fn write_u64(&mut self, i: u64) {
+    debug_assert!(self.state == 0);
+    self.state = i;
   }
 
   fn finish(&self) -> u64 {
@@ -13,7 +25,7 @@ impl std::hash::Hasher for PassThroughHasher {
   }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct BuildPassThroughHasher;
 
 impl std::hash::BuildHasher for BuildPassThroughHasher {
@@ -22,3 +34,32 @@ impl std::hash::BuildHasher for BuildPassThroughHasher {
     PassThroughHasher { state: 0 }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashMap, hash::BuildHasher};
+
+  use super::BuildPassThroughHasher;
+
+  #[test]
+  fn test_hash_is_key() {
+    assert_eq!(
+      BuildPassThroughHasher.hash_one(0x1234_5678_9abc_def0u64),
+      0x1234_5678_9abc_def0
+    );
+  }
+
+  #[test]
+  fn test_map() {
+    let mut map = HashMap::with_hasher(BuildPassThroughHasher);
+    for key in 0..100u64 {
+      map.insert(key.wrapping_mul(0x9e37_79b9_7f4a_7c15), key);
+    }
+    for key in 0..100u64 {
+      assert_eq!(
+        map.get(&key.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
+        Some(&key)
+      );
+    }
+  }
+}
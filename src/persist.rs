@@ -0,0 +1,361 @@
+use std::{
+  collections::HashMap,
+  hash::{BuildHasher, Hash},
+  io::{self, Read, Write},
+};
+
+use abstract_game::{Score, ScoreValue};
+
+/// Identifies files written by `write_table`.
+const TABLE_MAGIC: [u8; 8] = *b"COOPTBL\0";
+
+/// The version of the table and database formats. This must be incremented
+/// whenever the layout of the header, the entries or the score encoding
+/// changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Marks a score as a guaranteed tie in place of its tie depth.
+const GUARANTEED_TIE: u32 = u32::MAX;
+
+/// Games which can be saved to and loaded from a table file.
+pub trait EncodeGame: Sized {
+  /// A name for the game which is stored in the header of every table, so a
+  /// table of one game is never loaded as a table of another. This should be
+  /// changed whenever the encoding of the game changes.
+  const GAME_TYPE: &'static str;
+
+  fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+  fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+/// Transposition tables which can be saved to and loaded from a file.
+pub trait PersistTable {
+  /// Writes every entry of the table to `writer`, recording that the scores
+  /// were found by searches to at most `search_depth`. `writer` is not
+  /// buffered.
+  fn save<W: Write>(&self, writer: W, search_depth: u32) -> io::Result<()>;
+
+  /// Merges every entry from `reader` into the table, returning the search
+  /// depth recorded in the table's header. Fails without modifying the table if
+  /// the header does not match this table's game. `reader` is not buffered.
+  fn load<R: Read>(&mut self, reader: R) -> io::Result<u32>;
+}
+
+impl<G, S> PersistTable for HashMap<G, Score, S>
+where
+  G: EncodeGame + Hash + Eq,
+  S: BuildHasher,
+{
+  fn save<W: Write>(&self, writer: W, search_depth: u32) -> io::Result<()> {
+    write_table(writer, search_depth, self.len() as u64, |write_entry| {
+      self
+        .iter()
+        .try_for_each(|(game, &score)| write_entry(game, score))
+    })
+  }
+
+  fn load<R: Read>(&mut self, reader: R) -> io::Result<u32> {
+    read_table(reader, |game, score| merge_entry(self, game, score))
+  }
+}
+
+/// Merges `score` into the entry for `game` in `table`.
+pub(crate) fn merge_entry<G, S>(table: &mut HashMap<G, Score, S>, game: G, score: Score)
+where
+  G: Hash + Eq,
+  S: BuildHasher,
+{
+  table
+    .entry(game)
+    .and_modify(|entry| *entry = entry.merge(score))
+    .or_insert(score);
+}
+
+/// Writes a table with `len` entries to `writer`. `for_each_entry` must call
+/// the function it is passed with each entry of the table exactly once.
+pub(crate) fn write_table<G, W>(
+  mut writer: W,
+  search_depth: u32,
+  len: u64,
+  for_each_entry: impl FnOnce(&mut dyn FnMut(&G, Score) -> io::Result<()>) -> io::Result<()>,
+) -> io::Result<()>
+where
+  G: EncodeGame,
+  W: Write,
+{
+  write_header::<G, _>(&mut writer, &TABLE_MAGIC, search_depth, len)?;
+
+  let mut written = 0;
+  for_each_entry(&mut |game, score| {
+    written += 1;
+    game.encode(&mut writer)?;
+    write_score(&mut writer, score)
+  })?;
+  debug_assert_eq!(written, len);
+
+  writer.flush()
+}
+
+/// Reads a table written by `write_table`, calling `insert` with each entry and
+/// returning the search depth from its header. `insert` is only called once
+/// the header has been validated.
+pub(crate) fn read_table<G, R>(mut reader: R, mut insert: impl FnMut(G, Score)) -> io::Result<u32>
+where
+  G: EncodeGame,
+  R: Read,
+{
+  let (search_depth, len) = read_header::<G, _>(&mut reader, &TABLE_MAGIC)?;
+  for _ in 0..len {
+    let game = G::decode(&mut reader)?;
+    let score = read_score(&mut reader)?;
+    insert(game, score);
+  }
+
+  Ok(search_depth)
+}
+
+/// Writes the header shared by tables and databases of `G`, where `magic`
+/// identifies the kind of file and `len` is the number of entries following
+/// the header.
+pub(crate) fn write_header<G, W>(
+  writer: &mut W,
+  magic: &[u8; 8],
+  search_depth: u32,
+  len: u64,
+) -> io::Result<()>
+where
+  G: EncodeGame,
+  W: Write,
+{
+  writer.write_all(magic)?;
+  write_u32(writer, FORMAT_VERSION)?;
+  write_u32(writer, G::GAME_TYPE.len() as u32)?;
+  writer.write_all(G::GAME_TYPE.as_bytes())?;
+  write_u32(writer, search_depth)?;
+  writer.write_all(&len.to_le_bytes())
+}
+
+/// Reads a header written by `write_header`, returning the search depth and
+/// number of entries. Fails if the header is for a different kind of file, a
+/// different version of the format or a different game.
+pub(crate) fn read_header<G, R>(reader: &mut R, magic: &[u8; 8]) -> io::Result<(u32, u64)>
+where
+  G: EncodeGame,
+  R: Read,
+{
+  let mut file_magic = [0; 8];
+  reader.read_exact(&mut file_magic)?;
+  if &file_magic != magic {
+    return Err(invalid_data("unrecognized file type".to_owned()));
+  }
+
+  let version = read_u32(reader)?;
+  if version != FORMAT_VERSION {
+    return Err(invalid_data(format!(
+      "unsupported format version {version}, expected {FORMAT_VERSION}"
+    )));
+  }
+
+  let game_type_len = read_u32(reader)? as usize;
+  if game_type_len != G::GAME_TYPE.len() {
+    return Err(mismatched_game_type::<G>());
+  }
+  let mut game_type = vec![0; game_type_len];
+  reader.read_exact(&mut game_type)?;
+  if game_type != G::GAME_TYPE.as_bytes() {
+    return Err(mismatched_game_type::<G>());
+  }
+
+  let search_depth = read_u32(reader)?;
+  let mut len = [0; 8];
+  reader.read_exact(&mut len)?;
+  Ok((search_depth, u64::from_le_bytes(len)))
+}
+
+pub(crate) fn invalid_data(message: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn mismatched_game_type<G: EncodeGame>() -> io::Error {
+  invalid_data(format!("table is not for game type {}", G::GAME_TYPE))
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+  writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+  let mut bytes = [0; 4];
+  reader.read_exact(&mut bytes)?;
+  Ok(u32::from_le_bytes(bytes))
+}
+
+/// Returns the deepest depth at which `score` is known to be a tie, or
+/// `GUARANTEED_TIE` if it is a tie at every depth.
+fn tie_depth(score: Score) -> u32 {
+  if score.score() == ScoreValue::Tie {
+    return if score.fully_determined() {
+      GUARANTEED_TIE
+    } else {
+      score.determined_depth()
+    };
+  }
+
+  // Scores with a winner are determined up to the tie depth, and then again
+  // from the win depth onward.
+  let (mut lo, mut hi) = (0, score.determined_depth());
+  while hi - lo > 1 {
+    let mid = lo + (hi - lo) / 2;
+    if score.determined(mid) {
+      lo = mid;
+    } else {
+      hi = mid;
+    }
+  }
+  lo
+}
+
+/// Scores are encoded as the player who wins, if any, followed by the tie depth
+/// and the win depth.
+pub(crate) fn write_score<W: Write>(writer: &mut W, score: Score) -> io::Result<()> {
+  let (winner, win_depth) = match score.score() {
+    ScoreValue::Tie => (0, 0),
+    ScoreValue::CurrentPlayerWins => (1, score.determined_depth()),
+    ScoreValue::OtherPlayerWins => (2, score.determined_depth()),
+  };
+  writer.write_all(&[winner])?;
+  write_u32(writer, tie_depth(score))?;
+  write_u32(writer, win_depth)
+}
+
+pub(crate) fn read_score<R: Read>(reader: &mut R) -> io::Result<Score> {
+  let mut winner = [0];
+  reader.read_exact(&mut winner)?;
+  let tie_depth = read_u32(reader)?;
+  let win_depth = read_u32(reader)?;
+
+  let tie = if tie_depth == GUARANTEED_TIE {
+    Score::guaranteed_tie()
+  } else {
+    Score::tie(tie_depth)
+  };
+  match winner {
+    [0] => Ok(tie),
+    [1] => Ok(tie.merge(Score::win(win_depth))),
+    [2] => Ok(tie.merge(Score::lose(win_depth))),
+    [winner] => Err(invalid_data(format!("invalid winner {winner} in score"))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashMap, io};
+
+  use abstract_game::{Score, Solver};
+
+  use crate::{
+    persist::{read_score, write_score, PersistTable},
+    solvers::ttable_solver::TTSolver,
+    test::{gomoku::Gomoku, nim::Nim, tic_tac_toe::Ttt},
+  };
+
+  #[test]
+  fn test_score_round_trip() {
+    for score in [
+      Score::NO_INFO,
+      Score::tie(1),
+      Score::tie(7),
+      Score::guaranteed_tie(),
+      Score::win(1),
+      Score::lose(6),
+      Score::optimal_win(5),
+      Score::optimal_lose(4),
+      Score::tie(3).merge(Score::win(9)),
+      Score::tie(2).merge(Score::lose(5)),
+    ] {
+      let mut bytes = Vec::new();
+      write_score(&mut bytes, score).unwrap();
+      assert_eq!(read_score(&mut bytes.as_slice()).unwrap(), score);
+    }
+  }
+
+  #[test]
+  fn test_table_round_trip() {
+    let mut solver = TTSolver::new();
+    solver.best_move(&Ttt::new(), 9);
+
+    let mut bytes = Vec::new();
+    solver.table().save(&mut bytes, 9).unwrap();
+
+    let mut loaded = HashMap::new();
+    assert_eq!(loaded.load(bytes.as_slice()).unwrap(), 9);
+    assert_eq!(&loaded, solver.table());
+  }
+
+  #[test]
+  fn test_gomoku_round_trip() {
+    let mut solver = TTSolver::new();
+    solver.best_move(&Gomoku::new(3, 3, 3), 9);
+
+    let mut bytes = Vec::new();
+    solver.table().save(&mut bytes, 9).unwrap();
+
+    let mut loaded = TTSolver::new();
+    assert_eq!(loaded.load_table(bytes.as_slice()).unwrap(), 9);
+    assert_eq!(loaded.table(), solver.table());
+  }
+
+  #[test]
+  fn test_load_merges() {
+    let mut table = HashMap::new();
+    table.insert(Ttt::new(), Score::tie(3));
+    let mut bytes = Vec::new();
+    table.save(&mut bytes, 3).unwrap();
+
+    let mut loaded = HashMap::new();
+    loaded.insert(Ttt::new(), Score::win(8));
+    loaded.load(bytes.as_slice()).unwrap();
+    assert_eq!(
+      loaded.get(&Ttt::new()),
+      Some(&Score::win(8).merge(Score::tie(3)))
+    );
+  }
+
+  #[test]
+  fn test_load_wrong_game() {
+    let mut table = HashMap::new();
+    table.insert(Ttt::new(), Score::tie(3));
+    let mut bytes = Vec::new();
+    table.save(&mut bytes, 3).unwrap();
+
+    let mut loaded = HashMap::<Nim, Score>::new();
+    let err = loaded.load(bytes.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(loaded.is_empty());
+  }
+
+  #[test]
+  fn test_load_truncated() {
+    let mut table = HashMap::new();
+    table.insert(Ttt::new(), Score::tie(3));
+    let mut bytes = Vec::new();
+    table.save(&mut bytes, 3).unwrap();
+
+    let mut loaded = HashMap::<Ttt, Score>::new();
+    let err = loaded.load(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+  }
+
+  #[test]
+  fn test_load_wrong_version() {
+    let mut bytes = Vec::new();
+    HashMap::<Ttt, Score>::new().save(&mut bytes, 3).unwrap();
+    bytes[8] += 1;
+
+    let err = HashMap::<Ttt, Score>::new()
+      .load(bytes.as_slice())
+      .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  }
+}
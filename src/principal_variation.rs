@@ -0,0 +1,384 @@
+use std::{
+  collections::HashMap,
+  fmt::{self, Display, Formatter},
+  hash::{BuildHasher, Hash},
+};
+
+use abstract_game::{Game, GameResult, Score, ScoreValue};
+
+/// Tables of game scores, which can explain the results of the searches that
+/// filled them. This is implemented for the table of the cooperative solver and
+/// the tables of the serial solvers.
+///
+/// Tables of solvers with canonicalization enabled only store canonical states,
+/// so lookups of other states in the `HashMap` of a serial solver miss.
+pub trait ScoreTable<G: Game> {
+  /// Returns the score of `game` in the table, if it is there.
+  fn lookup_score(&self, game: &G) -> Option<Score>;
+
+  /// Computes the score of `game`, searched to `depth`, from the scores of each
+  /// of its children in the table, returning the score along with the best
+  /// move. Returns `None` if some unfinished child is not in the table, unless
+  /// the children that are in the table prove a win within `depth`.
+  fn score_from_children(&self, game: &G, depth: u32) -> Option<(Score, Option<G::Move>)> {
+    // If there are no possible moves, then the game is lost for the current
+    // player.
+    let mut accumulated_score = Score::lose(1);
+    let mut best_score = Score::NO_INFO;
+    let mut best_move = None;
+    let mut missing_children = false;
+    for (m, score) in child_scores(self, game, depth) {
+      let Some(score) = score else {
+        missing_children = true;
+        continue;
+      };
+      accumulated_score = accumulated_score.accumulate(score);
+      if best_move.is_none() || score.better(best_score) {
+        best_score = score;
+        best_move = Some(m);
+      }
+    }
+
+    if missing_children {
+      // Children are missing when they were cut off by alpha-beta pruning, or
+      // evicted from the table. A known win is still a win, though one of the
+      // missing children may win faster.
+      let score = accumulated_score.break_early();
+      return proves(score, depth, ScoreValue::CurrentPlayerWins).then_some((score, best_move));
+    }
+    Some((accumulated_score, best_move))
+  }
+
+  /// Returns the best move from `game` among the children which are finished or
+  /// in the table, or `None` if there are no such children.
+  fn best_known_move(&self, game: &G, depth: u32) -> Option<G::Move> {
+    child_scores(self, game, depth)
+      .filter_map(|(m, score)| score.map(|score| (m, score)))
+      .reduce(|best, next| if next.1.better(best.1) { next } else { best })
+      .map(|(m, _)| m)
+  }
+
+  /// Follows the best move from each state, starting from `game` searched to
+  /// `depth`, until reaching a state whose children have not all been resolved.
+  fn principal_variation(&self, game: &G, depth: u32) -> PrincipalVariation<G> {
+    let mut game = game.clone();
+    let mut steps = Vec::new();
+    for depth in (1..=depth).rev() {
+      if game.finished() != GameResult::NotFinished {
+        break;
+      }
+      match self.score_from_children(&game, depth) {
+        Some((score, Some(m))) => {
+          steps.push(VariationStep { mv: m, score });
+          game.make_move(m);
+        }
+        _ => break,
+      }
+    }
+
+    PrincipalVariation { steps }
+  }
+
+  /// Proves that one player can force a win from `game` within `depth` moves.
+  /// The tree follows a single winning move for the winner, and every reply for
+  /// the loser. Returns `None` if `game` is not a forced win within `depth`, or
+  /// if some state needed for the proof is not in the table.
+  fn proof_tree(&self, game: &G, depth: u32) -> Option<ProofTree<G>> {
+    match game.finished() {
+      GameResult::Win(_) => return Some(ProofTree { lines: Vec::new() }),
+      GameResult::Tie => return None,
+      GameResult::NotFinished => {}
+    }
+    let (score, _) = self.score_from_children(game, depth)?;
+    if !score.determined(depth) {
+      return None;
+    }
+    match score.score_at_depth(depth) {
+      ScoreValue::CurrentPlayerWins => winning_proof(self, game, depth),
+      ScoreValue::OtherPlayerWins => losing_proof(self, game, depth),
+      ScoreValue::Tie => None,
+    }
+  }
+}
+
+impl<G, S> ScoreTable<G> for HashMap<G, Score, S>
+where
+  G: Game + Hash + Eq,
+  S: BuildHasher,
+{
+  fn lookup_score(&self, game: &G) -> Option<Score> {
+    self.get(game).copied()
+  }
+}
+
+/// Iterates over each move from `game` along with the score of the resulting
+/// state from the perspective of `game`, if it is known.
+fn child_scores<'a, G, T>(
+  table: &'a T,
+  game: &'a G,
+  depth: u32,
+) -> impl Iterator<Item = (G::Move, Option<Score>)> + 'a
+where
+  G: Game,
+  T: ScoreTable<G> + ?Sized,
+{
+  game.each_move().map(move |m| {
+    let next_state = game.with_move(m);
+    let score = match next_state.finished() {
+      GameResult::Win(winner) => Some(if winner == game.current_player() {
+        Score::win(1)
+      } else {
+        Score::lose(1)
+      }),
+      GameResult::Tie => Some(Score::guaranteed_tie()),
+      // States searched to depth 0 are never committed, and count as ties to
+      // depth 1 for their parents.
+      GameResult::NotFinished if depth == 1 => Some(Score::tie(1)),
+      GameResult::NotFinished => table
+        .lookup_score(&next_state)
+        .map(|score| score.backstep()),
+    };
+    (m, score)
+  })
+}
+
+/// Whether `score` is determined to be `value` at `depth`.
+fn proves(score: Score, depth: u32, value: ScoreValue) -> bool {
+  score.determined(depth) && score.score_at_depth(depth) == value
+}
+
+/// Builds the proof tree of `game`, which the current player wins within
+/// `depth` moves. The fastest winning moves are tried first.
+fn winning_proof<G, T>(table: &T, game: &G, depth: u32) -> Option<ProofTree<G>>
+where
+  G: Game,
+  T: ScoreTable<G> + ?Sized,
+{
+  let mut winning_moves: Vec<_> = child_scores(table, game, depth)
+    .filter_map(|(m, score)| {
+      score
+        .filter(|&score| proves(score, depth, ScoreValue::CurrentPlayerWins))
+        .map(|score| (m, score))
+    })
+    .collect();
+  winning_moves.sort_by(|(_, score1), (_, score2)| score2.cmp(score1));
+
+  winning_moves.into_iter().find_map(|(m, score)| {
+    let replies = table.proof_tree(&game.with_move(m), depth - 1)?;
+    Some(ProofTree {
+      lines: vec![ProofLine {
+        mv: m,
+        score,
+        replies,
+      }],
+    })
+  })
+}
+
+/// Builds the proof tree of `game`, which the current player loses within
+/// `depth` moves, by refuting every move.
+fn losing_proof<G, T>(table: &T, game: &G, depth: u32) -> Option<ProofTree<G>>
+where
+  G: Game,
+  T: ScoreTable<G> + ?Sized,
+{
+  let lines = child_scores(table, game, depth)
+    .map(|(m, score)| {
+      let score = score?;
+      if !proves(score, depth, ScoreValue::OtherPlayerWins) {
+        return None;
+      }
+      let replies = table.proof_tree(&game.with_move(m), depth - 1)?;
+      Some(ProofLine {
+        mv: m,
+        score,
+        replies,
+      })
+    })
+    .collect::<Option<_>>()?;
+  Some(ProofTree { lines })
+}
+
+/// A move of a principal variation.
+#[derive(Clone, Debug)]
+pub struct VariationStep<G: Game> {
+  pub mv: G::Move,
+  /// The score of the state `mv` is made from, from the perspective of the
+  /// player making it.
+  pub score: Score,
+}
+
+/// The sequence of best moves for both players from some state.
+#[derive(Clone, Debug)]
+pub struct PrincipalVariation<G: Game> {
+  pub steps: Vec<VariationStep<G>>,
+}
+
+impl<G: Game> PrincipalVariation<G> {
+  pub fn moves(&self) -> impl Iterator<Item = G::Move> + '_ {
+    self.steps.iter().map(|step| step.mv)
+  }
+}
+
+impl<G> Display for PrincipalVariation<G>
+where
+  G: Game,
+  G::Move: Display,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    for (ply, step) in self.steps.iter().enumerate() {
+      writeln!(f, "{}. {} {}", ply + 1, step.mv, step.score)?;
+    }
+    Ok(())
+  }
+}
+
+/// A move in a proof tree, along with the proof for the state it leads to.
+#[derive(Clone, Debug)]
+pub struct ProofLine<G: Game> {
+  pub mv: G::Move,
+  /// The score of making `mv`, from the perspective of the player making it.
+  pub score: Score,
+  pub replies: ProofTree<G>,
+}
+
+/// A proof that one player can force a win. The winner's states have one line,
+/// and the loser's states have a line for every move. Finished states have no
+/// lines.
+#[derive(Clone, Debug)]
+pub struct ProofTree<G: Game> {
+  pub lines: Vec<ProofLine<G>>,
+}
+
+impl<G: Game> ProofTree<G> {
+  fn fmt_indented(&self, f: &mut Formatter<'_>, indent: usize) -> fmt::Result
+  where
+    G::Move: Display,
+  {
+    for line in &self.lines {
+      writeln!(f, "{:indent$}{} {}", "", line.mv, line.score)?;
+      line.replies.fmt_indented(f, indent + 2)?;
+    }
+    Ok(())
+  }
+}
+
+impl<G> Display for ProofTree<G>
+where
+  G: Game,
+  G::Move: Display,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    self.fmt_indented(f, 0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use abstract_game::{Game, GameResult, ScoreValue, Solver};
+
+  use crate::{
+    cooperate::{CooperativeSolver, Options},
+    principal_variation::{ProofTree, ScoreTable},
+    solvers::ttable_solver::TTSolver,
+    test::{nim::Nim, tic_tac_toe::Ttt},
+  };
+
+  /// Checks that `tree` proves a win for the player to move in `game` if
+  /// `winner_to_move`, or for the other player otherwise.
+  fn check_proof<G: Game>(game: &G, tree: &ProofTree<G>, winner_to_move: bool) {
+    if game.finished() != GameResult::NotFinished {
+      assert!(tree.lines.is_empty());
+      assert!(matches!(game.finished(), GameResult::Win(_)));
+      assert!(!winner_to_move);
+      return;
+    }
+    if winner_to_move {
+      assert_eq!(tree.lines.len(), 1);
+    } else {
+      assert_eq!(tree.lines.len(), game.each_move().count());
+    }
+    for line in &tree.lines {
+      check_proof(&game.with_move(line.mv), &line.replies, !winner_to_move);
+    }
+  }
+
+  fn children(game: &Ttt) -> Vec<Ttt> {
+    game.each_move().map(|m| game.with_move(m)).collect()
+  }
+
+  /// Finds a tic tac toe state three moves in whose score is `score` when
+  /// searched to the end of the game.
+  fn find_ttt(score: ScoreValue) -> Ttt {
+    let mut solver = TTSolver::new();
+    children(&Ttt::new())
+      .iter()
+      .flat_map(children)
+      .flat_map(|game| children(&game))
+      .find(|game| solver.best_move(game, 6).0.score_at_depth(6) == score)
+      .unwrap()
+  }
+
+  #[test]
+  fn test_nim_principal_variation() {
+    let mut solver = TTSolver::new();
+    let (score, best_move) = solver.best_move(&Nim::new(10), 11);
+
+    let pv = solver.table().principal_variation(&Nim::new(10), 11);
+    assert_eq!(pv.moves().next(), best_move);
+    assert_eq!(pv.steps[0].score, score);
+    let mut game = Nim::new(10);
+    for m in pv.moves() {
+      game.make_move(m);
+    }
+    assert!(matches!(game.finished(), GameResult::Win(_)));
+  }
+
+  #[test]
+  fn test_nim_proof_tree() {
+    let mut solver = TTSolver::new();
+    solver.best_move(&Nim::new(10), 11);
+    let tree = solver.table().proof_tree(&Nim::new(10), 11).unwrap();
+    check_proof(&Nim::new(10), &tree, true);
+    // The first player takes one stick, leaving a multiple of 3.
+    assert_eq!(tree.lines[0].mv.to_string(), "1");
+
+    solver.best_move(&Nim::new(9), 10);
+    let tree = solver.table().proof_tree(&Nim::new(9), 10).unwrap();
+    check_proof(&Nim::new(9), &tree, false);
+  }
+
+  #[test]
+  fn test_ttt_proof_tree() {
+    for (score, winner_to_move) in [
+      (ScoreValue::CurrentPlayerWins, true),
+      (ScoreValue::OtherPlayerWins, false),
+    ] {
+      let game = find_ttt(score);
+      let mut solver = TTSolver::new();
+      solver.best_move(&game, 6);
+      let tree = solver.table().proof_tree(&game, 6).unwrap();
+      check_proof(&game, &tree, winner_to_move);
+    }
+
+    // Tic tac toe is a tie, so there is no proof of a win.
+    let mut solver = TTSolver::new();
+    solver.best_move(&Ttt::new(), 9);
+    assert!(solver.table().proof_tree(&Ttt::new(), 9).is_none());
+  }
+
+  #[test]
+  fn test_cooperative_table() {
+    let game = find_ttt(ScoreValue::CurrentPlayerWins);
+    let mut solver = CooperativeSolver::new(Options {
+      num_threads: 2,
+      ..Options::default()
+    });
+    let result = solver.solve(&game, 6).unwrap();
+
+    let table = solver.table().unwrap();
+    let pv = table.principal_variation(&game, 6);
+    assert_eq!(pv.moves().collect::<Vec<_>>(), result.principal_variation);
+    check_proof(&game, &table.proof_tree(&game, 6).unwrap(), true);
+  }
+}
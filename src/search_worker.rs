@@ -2,16 +2,31 @@ use std::{
   fmt::Display,
   hash::{BuildHasher, Hash},
   sync::Arc,
+  time::Instant,
 };
 
 use abstract_game::{Game, GameResult, Score};
 
+use crossbeam_queue::SegQueue;
+
 use crate::{
+  evaluate::Evaluation,
   global_data::{GlobalData, LookupResult},
   metrics::Metrics,
+  null_lock::NullLock,
   stack::{Stack, StackType},
 };
 
+/// Stacks are only split at frames being searched at least this deep. Any
+/// shallower, and the children are too small to be worth handing to another
+/// worker.
+const MIN_SPLIT_DEPTH: u32 = 4;
+
+/// Workers charge the nodes they search against the search budget in batches
+/// of this many, which is also how often they check whether the search has been
+/// stopped while working on a stack.
+const BUDGET_CHECK_INTERVAL: u64 = 256;
+
 pub struct WorkerData<G, H>
 where
   G: Game,
@@ -22,6 +37,9 @@ where
 
   globals: Arc<GlobalData<G, H>>,
   metrics: Metrics,
+  /// The number of nodes searched which have not been charged against the
+  /// search budget yet.
+  uncharged_nodes: u64,
 }
 
 impl<G, H> WorkerData<G, H>
@@ -33,44 +51,123 @@ where
       thread_idx,
       globals,
       metrics: Metrics::new(),
+      uncharged_nodes: 0,
     }
   }
 }
 
-pub fn start_worker<G, H>(mut data: WorkerData<G, H>)
+/// Frees a stack which has no frames left. If it was the last outstanding child
+/// of a split stack, then the parent is revived and pushed back onto `queue`,
+/// or freed in turn if reviving it resolved its last frame.
+pub fn retire_stack<G, H>(
+  globals: &GlobalData<G, H>,
+  mut stack_ptr: *mut Stack<G>,
+  queue: &SegQueue<NullLock<*mut Stack<G>>>,
+  metrics: &mut Metrics,
+) where
+  G: Display + Game + Hash + Eq + 'static,
+  G::Move: Display,
+  H: BuildHasher + Clone,
+{
+  loop {
+    let parent_ptr = match unsafe { &*stack_ptr }.stack_type() {
+      StackType::Root => None,
+      StackType::Child { parent } => Some(*parent),
+    };
+    globals.free_stack(stack_ptr);
+
+    let parent_ptr = match parent_ptr {
+      Some(parent_ptr) => parent_ptr,
+      None => return,
+    };
+    if !Stack::resolve_outstanding_child(parent_ptr) {
+      // Some other child is still being explored, and whoever finishes last
+      // will revive the parent.
+      return;
+    }
+    if globals.stopped() {
+      // Some of the other children may have been abandoned, so the parent
+      // can't be resolved.
+      globals.abandon_stack(parent_ptr, queue, metrics);
+      return;
+    }
+
+    globals.revive_split(parent_ptr, queue, metrics);
+    if unsafe { &*parent_ptr }.bottom_frame().is_some() {
+      globals.requeue(parent_ptr, queue);
+      return;
+    }
+    stack_ptr = parent_ptr;
+  }
+}
+
+/// Runs a worker until every stack in the search has been freed, returning the
+/// metrics it collected along the way.
+pub fn start_worker<G, H>(mut data: WorkerData<G, H>) -> Metrics
 where
   G: Display + Game + Hash + Eq + 'static,
   G::Move: Display,
   H: BuildHasher + Clone,
 {
+  let start = Instant::now();
   let queue = data.globals.queue(data.thread_idx);
 
   loop {
-    let unit = queue.pop();
-
-    let stack_ptr = match unit {
-      Some(stack_ptr) => *stack_ptr,
-      // TODO: steal
-      None => break,
+    let stack_ptr = match data.globals.take_work(data.thread_idx, &mut data.metrics) {
+      Some(stack_ptr) => stack_ptr,
+      None => {
+        // Other workers may still be holding stacks, and any of them can
+        // produce more work (e.g. by reviving suspended stacks), so we can
+        // only stop once every stack in the search has been freed. Until then,
+        // sleep until something is pushed onto a queue. If the search was
+        // aborted, some stacks will never be freed, so there is no point
+        // waiting for them.
+        if data.globals.finished() || data.globals.aborted() {
+          break;
+        }
+        data.globals.park(data.thread_idx);
+        continue;
+      }
     };
+    if data.globals.stopped() {
+      data
+        .globals
+        .abandon_stack(stack_ptr, queue, &mut data.metrics);
+      continue;
+    }
     // We own stack here, so we can access it without atomics.
     let stack = unsafe { &mut *stack_ptr };
 
     loop {
       if stack.bottom_frame().is_none() {
         // We've finished exploring this stack frame.
-        match stack.stack_type() {
-          StackType::Root => {}
-          StackType::Child { parent } => {
-            Stack::resolve_outstanding_child(*parent);
-          }
-        }
+        retire_stack(&data.globals, stack_ptr, queue, &mut data.metrics);
+        break;
+      }
 
-        // Delete the stack pointer.
-        unsafe { drop(Box::from_raw(stack_ptr)) };
+      // Only the owner of a state may look it up, so if we don't own the bottom
+      // state, hand the stack to whoever does before visiting it.
+      if let Some(owner) = data
+        .globals
+        .owner(stack.bottom_frame().unwrap().game())
+        .filter(|&owner| owner != data.thread_idx)
+      {
+        data.globals.route(stack_ptr, owner, &mut data.metrics);
         break;
       }
 
+      data.metrics.nodes += 1;
+      data.uncharged_nodes += 1;
+      if data.uncharged_nodes == BUDGET_CHECK_INTERVAL {
+        data.uncharged_nodes = 0;
+        if data.globals.charge_nodes(BUDGET_CHECK_INTERVAL) {
+          data
+            .globals
+            .abandon_stack(stack_ptr, queue, &mut data.metrics);
+          break;
+        }
+      }
+
       // println!(
       //   "\n[{}] Exploring\n{}\n(depth {})",
       //   data.thread_idx,
@@ -83,6 +180,7 @@ where
       let game_result = game.finished();
       match game_result {
         GameResult::Win(winner) => {
+          data.metrics.terminal_states += 1;
           // Since scores indicating a player is currently winning are not
           // representable, we construct scores for the parent of this frame that
           // indicate the opposite player will can in one turn.
@@ -102,57 +200,92 @@ where
           //   "    [{}] parent score is {score_for_parent}",
           //   data.thread_idx
           // );
-          stack.pop_with_backstepped_score(score_for_parent);
+          stack.pop_with_backstepped_score(score_for_parent, Evaluation::new(score_for_parent, 0));
         }
         GameResult::Tie => {
+          data.metrics.terminal_states += 1;
           // println!(
           //   "    [{}] parent score is {}",
           //   data.thread_idx,
           //   Score::guaranteed_tie()
           // );
-          stack.pop_with_backstepped_score(Score::guaranteed_tie());
+          stack.pop_with_backstepped_score(
+            Score::guaranteed_tie(),
+            Evaluation::new(Score::guaranteed_tie(), 0),
+          );
         }
         GameResult::NotFinished => {
           // First, check if there is an immediate winning move.
-
-          match data.globals.get_or_queue(stack_ptr, &mut data.metrics) {
-            LookupResult::Found { score } => {
-              // Update best score in frame
-              // println!("    [{}] Found", data.thread_idx);
-              stack.pop_with_score(score);
-            }
-            // If the state was not found, then we can continue on exploring it.
-            LookupResult::NotFound => {
-              // println!("    [{}] Inserted placeholder in table", data.thread_idx);
-            }
-            // If the state was queued, then it was added to the list of states
-            // waiting on the result of some game state. After this result is
-            // found, all states which are pending are re-added to some worker's
-            // queue (randomly distributed).
-            LookupResult::Queued => {
-              // println!("    [{}] Queued on other state", data.thread_idx);
-              break;
+          if !data.globals.resolve_immediate_win(stack, &mut data.metrics) {
+            match data.globals.get_or_queue(stack_ptr, &mut data.metrics) {
+              LookupResult::Found { score, evaluation } => {
+                // Update best score in frame
+                // println!("    [{}] Found", data.thread_idx);
+                stack.pop_with_score(score, evaluation);
+              }
+              // If the state was not found, then we can continue on exploring it.
+              // If other workers are idle, hand them the children of this state
+              // instead of exploring them all ourselves. Idle workers can't
+              // steal when stacks are routed to the owners of their states, so
+              // then the children are handed to their owners whenever the next
+              // one belongs to another worker.
+              LookupResult::NotFound => {
+                // println!("    [{}] Inserted placeholder in table", data.thread_idx);
+                if stack.bottom_depth() >= MIN_SPLIT_DEPTH
+                  && data.globals.should_split(stack, data.thread_idx)
+                {
+                  if !data.globals.split(stack_ptr, queue) {
+                    break;
+                  }
+                  // Every child finished before we were done splitting, so we
+                  // are still responsible for the stack.
+                  if data.globals.stopped() {
+                    data
+                      .globals
+                      .abandon_stack(stack_ptr, queue, &mut data.metrics);
+                    break;
+                  }
+                  data
+                    .globals
+                    .revive_split(stack_ptr, queue, &mut data.metrics);
+                  continue;
+                }
+              }
+              // If the state was queued, then it was added to the list of states
+              // waiting on the result of some game state. After this result is
+              // found, all states which are pending are re-added to some worker's
+              // queue (randomly distributed).
+              LookupResult::Queued => {
+                // println!("    [{}] Queued on other state", data.thread_idx);
+                break;
+              }
             }
           }
         }
       }
 
-      data.globals.explore_next_state(stack_ptr, queue);
+      data
+        .globals
+        .explore_next_state(stack_ptr, queue, &mut data.metrics);
     }
   }
 
-  // println!("Worker {} done: {:?}", data.thread_idx, data.metrics);
+  // The search is already over, so this only needs to be counted.
+  data.globals.record_nodes(data.uncharged_nodes);
+
+  data.metrics.wall_time = start.elapsed();
+  data.metrics
 }
 
 #[cfg(test)]
 mod tests {
-  use std::{sync::Arc, time::SystemTime};
+  use std::{sync::Arc, thread, time::SystemTime};
 
   use abstract_game::{Game, GameResult};
 
   use crate::{
-    global_data::GlobalData,
-    null_lock::NullLock,
+    global_data::{GlobalData, LookupResult, StackCounts},
+    metrics::Metrics,
     stack::Stack,
     test::{
       gomoku::Gomoku,
@@ -168,12 +301,10 @@ mod tests {
   fn test_nim_serial() {
     const STICKS: u32 = 100;
     let globals = Arc::new(GlobalData::new(STICKS + 1, 1));
-    globals.queue(0).push(unsafe {
-      NullLock::new(Box::into_raw(Box::new(Stack::make_root(
-        Nim::new(STICKS),
-        STICKS + 1,
-      ))))
-    });
+    globals.queue_new_stack(
+      0,
+      Box::into_raw(Box::new(Stack::make_root(Nim::new(STICKS), STICKS + 1))),
+    );
 
     start_worker(WorkerData::new(0, globals.clone()));
 
@@ -188,11 +319,12 @@ mod tests {
   fn test_ttt_serial() {
     const DEPTH: u32 = 10;
     let globals = Arc::new(GlobalData::new(DEPTH, 1));
-    globals
-      .queue(0)
-      .push(unsafe { NullLock::new(Box::into_raw(Box::new(Stack::make_root(Ttt::new(), DEPTH)))) });
+    globals.queue_new_stack(
+      0,
+      Box::into_raw(Box::new(Stack::make_root(Ttt::new(), DEPTH))),
+    );
 
-    start_worker(WorkerData::new(0, globals.clone()));
+    let metrics = start_worker(WorkerData::new(0, globals.clone()));
 
     // The table should contain the completed initial state.
     assert!(globals
@@ -200,6 +332,15 @@ mod tests {
       .table()
       .contains_key(&Ttt::new()));
 
+    // With only one worker, every state is claimed and committed exactly once,
+    // and nothing is ever stolen or suspended.
+    let table_size = globals.resolved_states_table().table().len() as u64;
+    assert_eq!(metrics.claims, table_size);
+    assert_eq!(metrics.commits, table_size);
+    assert_eq!(metrics.steals, 0);
+    assert_eq!(metrics.revivals, 0);
+    assert_eq!(metrics.queues, 0);
+
     for state in globals.resolved_states_table().table().iter() {
       // Terminal states should not be stored in the table.
       assert_eq!(state.key().finished(), GameResult::NotFinished);
@@ -220,17 +361,139 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_ttt_steal() {
+    const DEPTH: u32 = 10;
+    const THREADS: u32 = 4;
+    let globals = Arc::new(GlobalData::new(DEPTH, THREADS));
+    // All of the work starts on worker 0's queue, and worker 0 never runs, so
+    // the other workers can only make progress by stealing.
+    globals.queue_new_stack(
+      0,
+      Box::into_raw(Box::new(Stack::make_root(Ttt::new(), DEPTH))),
+    );
+
+    let thread_handles: Vec<_> = (1..THREADS)
+      .map(|thread_idx| {
+        let globals = globals.clone();
+        thread::spawn(move || start_worker(WorkerData::new(thread_idx, globals)))
+      })
+      .collect();
+
+    let metrics = thread_handles
+      .into_iter()
+      .map(|thread| thread.join().unwrap())
+      .sum::<Metrics>();
+    assert!(metrics.steals > 0);
+
+    assert!(globals.finished());
+    assert_eq!(globals.stack_counts(), StackCounts::default());
+    let score = globals.resolved_states_table().get(&Ttt::new());
+    assert!(score.is_some());
+    assert!(score
+      .unwrap()
+      .compatible(Ttt::new().compute_expected_score(DEPTH)));
+  }
+
+  #[test]
+  fn test_ttt_split_root() {
+    const DEPTH: u32 = 10;
+    let globals = Arc::new(GlobalData::new(DEPTH, 1));
+    globals.queue_new_stack(
+      0,
+      Box::into_raw(Box::new(Stack::make_root(Ttt::new(), DEPTH))),
+    );
+
+    // Claim the root state and split it by hand, leaving only the children
+    // for the worker to explore.
+    let stack_ptr = globals.take_work(0, &mut Metrics::new()).unwrap();
+    assert!(matches!(
+      globals.get_or_queue(stack_ptr, &mut Metrics::new()),
+      LookupResult::NotFound
+    ));
+    assert!(!globals.split(stack_ptr, globals.queue(0)));
+    assert_eq!(
+      globals.stack_counts(),
+      StackCounts {
+        queued: 9,
+        split: 1,
+        ..StackCounts::default()
+      }
+    );
+
+    start_worker(WorkerData::new(0, globals.clone()));
+
+    // The last child to finish should have revived the root, which then
+    // committed its score.
+    assert!(globals.finished());
+    assert_eq!(globals.stack_counts(), StackCounts::default());
+    let score = globals.resolved_states_table().get(&Ttt::new());
+    assert!(score.is_some());
+    assert!(score
+      .unwrap()
+      .compatible(Ttt::new().compute_expected_score(DEPTH)));
+  }
+
+  #[test]
+  fn test_should_split_transposition_driven() {
+    const DEPTH: u32 = 10;
+    const THREADS: u32 = 4;
+    let mut globals = GlobalData::new(DEPTH, THREADS);
+    globals.set_transposition_driven(true);
+    let globals = Arc::new(globals);
+    let owner = globals.owner(&Ttt::new()).unwrap();
+    globals.queue_new_stack(
+      owner,
+      Box::into_raw(Box::new(Stack::make_root(Ttt::new(), DEPTH))),
+    );
+
+    let stack_ptr = globals.take_work(owner, &mut Metrics::new()).unwrap();
+    assert!(matches!(
+      globals.get_or_queue(stack_ptr, &mut Metrics::new()),
+      LookupResult::NotFound
+    ));
+
+    // Exploring the next child depth-first is only worse than splitting when
+    // that child has to be handed to another worker.
+    let stack = unsafe { &*stack_ptr };
+    let frame = stack.bottom_frame().unwrap();
+    let child = frame.game().with_move(frame.current_move().unwrap());
+    let child_owner = globals.owner(&child).unwrap();
+    for thread_idx in 0..THREADS {
+      assert_eq!(
+        globals.should_split(stack, thread_idx),
+        thread_idx != child_owner
+      );
+    }
+
+    globals.explore_next_state(stack_ptr, globals.queue(owner), &mut Metrics::new());
+    globals.requeue(stack_ptr, globals.queue(owner));
+    let thread_handles: Vec<_> = (0..THREADS)
+      .map(|thread_idx| {
+        let globals = globals.clone();
+        thread::spawn(move || start_worker(WorkerData::new(thread_idx, globals)))
+      })
+      .collect();
+    for thread in thread_handles.into_iter() {
+      thread.join().unwrap();
+    }
+
+    assert!(globals.finished());
+    let score = globals.resolved_states_table().get(&Ttt::new());
+    assert!(score
+      .unwrap()
+      .compatible(Ttt::new().compute_expected_score(DEPTH)));
+  }
+
   #[test]
   #[ignore]
   fn test_gomoku_4x4_serial() {
     const DEPTH: u32 = 16;
     let globals = Arc::new(GlobalData::new(DEPTH, 1));
-    globals.queue(0).push(unsafe {
-      NullLock::new(Box::into_raw(Box::new(Stack::make_root(
-        Gomoku::new(4, 4, 4),
-        DEPTH,
-      ))))
-    });
+    globals.queue_new_stack(
+      0,
+      Box::into_raw(Box::new(Stack::make_root(Gomoku::new(4, 4, 4), DEPTH))),
+    );
 
     println!("Solving...");
     let start = SystemTime::now();
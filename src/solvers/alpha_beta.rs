@@ -2,14 +2,62 @@ use std::marker::PhantomData;
 
 use abstract_game::{Game, GameResult, Score, ScoreValue, Solver};
 
-pub struct AlphaBeta<G>(PhantomData<G>);
+use crate::{
+  immediate_win::{has_immediate_win, ImmediateWin},
+  move_order::{MoveOrderer, NoOrdering},
+};
+
+pub struct AlphaBeta<G, O = NoOrdering> {
+  orderer: O,
+  immediate_win_check: Option<fn(&G) -> bool>,
+  _game: PhantomData<G>,
+}
+
+impl<G: Game> Default for AlphaBeta<G> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
 
 impl<G: Game> AlphaBeta<G> {
   pub fn new() -> Self {
-    Self(PhantomData)
+    Self {
+      orderer: NoOrdering,
+      immediate_win_check: None,
+      _game: PhantomData,
+    }
   }
+}
 
-  fn score_for_game(game: &G, depth: u32, alpha: ScoreValue, beta: ScoreValue) -> Score {
+impl<G: Game, O: MoveOrderer<G>> AlphaBeta<G, O> {
+  /// Makes the solver search moves in the order chosen by `orderer`.
+  pub fn with_move_orderer<O2: MoveOrderer<G>>(self, orderer: O2) -> AlphaBeta<G, O2> {
+    AlphaBeta {
+      orderer,
+      immediate_win_check: self.immediate_win_check,
+      _game: PhantomData,
+    }
+  }
+
+  /// Makes the solver resolve states where the player to move has an immediate
+  /// win to a win in one, without searching their moves.
+  pub fn with_immediate_wins(self) -> Self
+  where
+    G: ImmediateWin,
+  {
+    Self {
+      immediate_win_check: Some(has_immediate_win::<G>),
+      ..self
+    }
+  }
+
+  fn moves(&self, game: &G, depth: u32) -> Vec<G::Move> {
+    let mut moves: Vec<_> = game.each_move().collect();
+    self.orderer.order_moves(game, depth, None, &mut moves);
+    moves
+  }
+
+  fn score_for_game(&self, game: &G, depth: u32, alpha: ScoreValue, beta: ScoreValue) -> Score {
     match game.finished() {
       GameResult::Win(player) => {
         if player == game.current_player() {
@@ -19,24 +67,32 @@ impl<G: Game> AlphaBeta<G> {
         }
       }
       GameResult::Tie => Score::guaranteed_tie(),
-      GameResult::NotFinished => {
-        Self::solve_impl(game, depth, beta.invert(), alpha.invert()).backstep()
-      }
+      GameResult::NotFinished => self
+        .solve_impl(game, depth, beta.invert(), alpha.invert())
+        .backstep(),
     }
   }
 
-  fn solve_impl(game: &G, depth: u32, alpha: ScoreValue, beta: ScoreValue) -> Score {
+  fn solve_impl(&self, game: &G, depth: u32, alpha: ScoreValue, beta: ScoreValue) -> Score {
     debug_assert!(matches!(game.finished(), GameResult::NotFinished));
     debug_assert!(alpha <= beta);
     if depth == 0 {
       return Score::NO_INFO;
     }
+    if self
+      .immediate_win_check
+      .is_some_and(|immediate_win_check| immediate_win_check(game))
+    {
+      return Score::win(1);
+    }
 
     let mut acc = Score::lose(1);
-    for next_game in game.each_move().map(|m| game.with_move(m)) {
-      let score = Self::score_for_game(&next_game, depth - 1, alpha.max(acc.score()), beta);
+    for m in self.moves(game, depth) {
+      let next_game = game.with_move(m);
+      let score = self.score_for_game(&next_game, depth - 1, alpha.max(acc.score()), beta);
       acc = acc.accumulate(score);
       if score.score() >= beta {
+        self.orderer.record_cutoff(game, depth, m);
         return acc.break_early();
       }
     }
@@ -45,7 +101,7 @@ impl<G: Game> AlphaBeta<G> {
   }
 }
 
-impl<G: Game> Solver for AlphaBeta<G> {
+impl<G: Game, O: MoveOrderer<G>> Solver for AlphaBeta<G, O> {
   type Game = G;
 
   fn best_move(&mut self, game: &G, depth: u32) -> (Score, Option<G::Move>) {
@@ -54,19 +110,23 @@ impl<G: Game> Solver for AlphaBeta<G> {
       return (Score::NO_INFO, None);
     }
 
-    let mut alpha = ScoreValue::OtherPlayerWins;
-
-    game
-      .each_move()
-      .map(|m| {
-        let next_game = game.with_move(m);
-        let score = Self::score_for_game(&next_game, depth, alpha, ScoreValue::CurrentPlayerWins);
-        alpha = alpha.max(score.score());
-        (score, Some(m))
-      })
-      .max_by_key(|(score, _)| score.clone())
-      // If you can't make a move, you lose.
-      .unwrap_or((Score::lose(1), None))
+    // As in `solve_impl`, later moves only get bounds from their narrowed
+    // windows.
+    let mut acc = Score::lose(1);
+    let mut best = None;
+    for m in self.moves(game, depth) {
+      let score = self.score_for_game(
+        &game.with_move(m),
+        depth,
+        acc.score(),
+        ScoreValue::CurrentPlayerWins,
+      );
+      acc = acc.accumulate(score);
+      if best.is_none_or(|(best_score, _)| score > best_score) {
+        best = Some((score, m));
+      }
+    }
+    (acc, best.map(|(_, m)| m))
   }
 }
 
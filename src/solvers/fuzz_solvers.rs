@@ -10,8 +10,10 @@ use rstest::rstest;
 use rstest_reuse::{apply, template};
 
 use crate::{
+  cooperate::{CooperativeSolver, Options},
+  move_order::{HistoryHeuristic, KillerMoves, TableScoreOrdering},
   solvers::{
-    alpha_beta::AlphaBeta, iter_deep::IterativeDeepening, simple::SimpleSolver,
+    alpha_beta::AlphaBeta, iter_deep::IterativeDeepening, lazy_smp::LazySmp, simple::SimpleSolver,
     ttable_alpha_beta::TTAlphaBeta, ttable_solver::TTSolver,
   },
   test::gomoku::Gomoku,
@@ -36,8 +38,34 @@ fn solvers(
   #[values(
     (SimpleSolver::new(), AlphaBeta::new()),
     (SimpleSolver::new(), TTSolver::new()),
+    (SimpleSolver::new(), AlphaBeta::new().with_move_orderer((KillerMoves::new(), HistoryHeuristic::new()))),
     (SimpleSolver::new(), TTAlphaBeta::new()),
+    (SimpleSolver::new(), TTAlphaBeta::new().with_move_orderer((TableScoreOrdering, KillerMoves::new()))),
     (SimpleSolver::new(), IterativeDeepening::new()),
+    (SimpleSolver::new(), LazySmp::new(4)),
+    (
+      SimpleSolver::new(),
+      CooperativeSolver::new(Options { num_threads: 4, unit_depth: 2, ..Options::default() }),
+    ),
+    (
+      SimpleSolver::new(),
+      CooperativeSolver::new(Options {
+        num_threads: 4,
+        unit_depth: 2,
+        alpha_beta: true,
+        ..Options::default()
+      }),
+    ),
+    (
+      SimpleSolver::new(),
+      CooperativeSolver::new(Options {
+        num_threads: 4,
+        unit_depth: 2,
+        alpha_beta: true,
+        ..Options::default()
+      })
+      .with_move_orderer((TableScoreOrdering, HistoryHeuristic::new())),
+    ),
   )]
   solvers: (impl Solver, impl Solver),
   #[values(
@@ -12,11 +12,16 @@ use rstest::rstest;
 use rstest_reuse::{apply, template};
 
 use crate::{
+  canonicalize::Canonicalize,
+  move_order::{HeuristicOrdering, KillerMoves, MoveOrderer, TableScoreOrdering},
   solvers::{
     iter_deep::IterativeDeepening, simple::SimpleSolver, ttable_alpha_beta::TTAlphaBeta,
     ttable_solver::TTSolver,
   },
-  test::gomoku::Gomoku,
+  test::{
+    gomoku::{Gomoku, GomokuMove},
+    tic_tac_toe::Ttt,
+  },
 };
 
 trait HasTable<G, S> {
@@ -29,13 +34,23 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> HasTable<G, S> for TTSolver<G,
   }
 }
 
-impl<G: Game + Hash + Eq, S: BuildHasher + Clone> HasTable<G, S> for TTAlphaBeta<G, S> {
+impl<G, S, O> HasTable<G, S> for TTAlphaBeta<G, S, O>
+where
+  G: Game + Hash + Eq,
+  S: BuildHasher + Clone,
+  O: MoveOrderer<G>,
+{
   fn table(&self) -> &HashMap<G, Score, S> {
     TTAlphaBeta::table(self)
   }
 }
 
-impl<G: Game + Hash + Eq, S: BuildHasher + Clone> HasTable<G, S> for IterativeDeepening<G, S> {
+impl<G, S, O> HasTable<G, S> for IterativeDeepening<G, S, O>
+where
+  G: Game + Hash + Eq,
+  S: BuildHasher + Clone,
+  O: MoveOrderer<G>,
+{
   fn table(&self) -> &HashMap<G, Score, S> {
     IterativeDeepening::table(self)
   }
@@ -70,6 +85,7 @@ fn test_ground_truth_table_solver<G: Game<Move: Ord> + Hash + Eq>(starting_state
 fn solvers(
   #[values(
     (TTSolver::new(), TTAlphaBeta::new()),
+    (TTSolver::new(), TTAlphaBeta::new().with_move_orderer((TableScoreOrdering, KillerMoves::new()))),
     (TTSolver::new(), IterativeDeepening::new(),
   ))]
   solvers: (impl Solver + HasTable, impl Solver + HasTable),
@@ -110,3 +126,84 @@ fn test_solve<G: Game<Move: Ord> + Hash + Eq, S: BuildHasher + Clone>(
     );
   }
 }
+
+#[rstest]
+#[case((TTSolver::new(), TTSolver::new().with_canonicalization()))]
+#[case((TTAlphaBeta::new(), TTAlphaBeta::new().with_canonicalization()))]
+#[case((IterativeDeepening::new(), IterativeDeepening::new().with_canonicalization()))]
+#[gtest]
+fn test_canonicalization<S: BuildHasher + Clone>(
+  #[case] solvers: (
+    impl Solver<Game = Ttt> + HasTable<Ttt, S>,
+    impl Solver<Game = Ttt> + HasTable<Ttt, S>,
+  ),
+) {
+  const DEPTH: u32 = 9;
+  let (mut solver, mut canonical_solver) = solvers;
+
+  let (score, _) = solver.best_move(&Ttt::new(), DEPTH);
+  let (canonical_score, best_move) = canonical_solver.best_move(&Ttt::new(), DEPTH);
+  assert_eq!(canonical_score, score);
+  assert!(Ttt::new().each_move().any(|m| Some(m) == best_move));
+
+  assert!(canonical_solver.table().len() < solver.table().len());
+  for (game, score) in canonical_solver.table() {
+    assert_eq!(&game.canonicalize(), game);
+    if let Some(expected_score) = solver.table().get(game) {
+      assert!(
+        score.compatible(*expected_score),
+        "{score} vs {expected_score} for state\n{game:?}"
+      );
+    }
+  }
+}
+
+#[gtest]
+fn test_gomoku_canonicalization() {
+  const DEPTH: u32 = 12;
+  let gomoku = Gomoku::new(4, 3, 3);
+  let mut solver = TTSolver::new();
+  let mut canonical_solver = TTSolver::new().with_canonicalization();
+
+  let (score, _) = solver.best_move(&gomoku, DEPTH);
+  let (canonical_score, _) = canonical_solver.best_move(&gomoku, DEPTH);
+  assert_eq!(canonical_score, score);
+  assert!(canonical_solver.table().len() < solver.table().len());
+
+  // Opposite corners of the board are symmetric, so solving from one of them
+  // only finds entries the other one already made.
+  let game = gomoku.with_move(GomokuMove::new(0, 0));
+  let mirrored = gomoku.with_move(GomokuMove::new(3, 2));
+  assert_eq!(game.canonicalize(), mirrored.canonicalize());
+  assert!(canonical_solver.table().contains_key(&game.canonicalize()));
+  let entries = canonical_solver.table().len();
+  canonical_solver.best_move(&mirrored, DEPTH - 1);
+  assert_eq!(canonical_solver.table().len(), entries);
+}
+
+#[rstest]
+#[case(TTAlphaBeta::new().with_move_orderer(HeuristicOrdering))]
+#[case(IterativeDeepening::new().with_move_orderer((TableScoreOrdering, HeuristicOrdering)))]
+#[case(TTAlphaBeta::new().with_immediate_wins())]
+#[case(IterativeDeepening::new().with_immediate_wins())]
+#[gtest]
+fn test_gomoku_pruning<S: BuildHasher + Clone>(
+  #[case] mut solver: impl Solver<Game = Gomoku> + HasTable<Gomoku, S>,
+) {
+  const DEPTH: u32 = 16;
+  let gomoku = Gomoku::new(4, 4, 3);
+  let mut ground_truth = TTSolver::new();
+  ground_truth.best_move(&gomoku, DEPTH);
+  let mut unordered = TTAlphaBeta::new();
+  unordered.best_move(&gomoku, DEPTH);
+
+  solver.best_move(&gomoku, DEPTH);
+  for (game, score) in solver.table() {
+    let expected_score = *ground_truth.table().get(game).unwrap();
+    assert!(
+      score.compatible(expected_score),
+      "{score} vs {expected_score} for state\n{game:?}"
+    );
+  }
+  assert!(solver.table().len() < unordered.table().len());
+}
@@ -0,0 +1,208 @@
+use std::marker::PhantomData;
+
+use abstract_game::{GameResult, Score, Solver};
+
+use crate::{
+  evaluate::{Evaluate, Evaluation},
+  move_order::{MoveOrderer, NoOrdering},
+};
+
+/// A depth-limited alpha-beta search which values the states at its horizon
+/// with the game's `Evaluate` heuristic, and picks the move with the best
+/// evaluation. Alongside the evaluation, it accumulates the same proven `Score`
+/// as `AlphaBeta`, which is only as determined as the pruning allows.
+pub struct HeuristicAlphaBeta<G, O = NoOrdering> {
+  orderer: O,
+  _game: PhantomData<G>,
+}
+
+impl<G: Evaluate> HeuristicAlphaBeta<G> {
+  pub fn new() -> Self {
+    Self {
+      orderer: NoOrdering,
+      _game: PhantomData,
+    }
+  }
+}
+
+impl<G: Evaluate> Default for HeuristicAlphaBeta<G> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<G: Evaluate, O: MoveOrderer<G>> HeuristicAlphaBeta<G, O> {
+  /// Makes the solver search moves in the order chosen by `orderer`.
+  pub fn with_move_orderer<O2: MoveOrderer<G>>(self, orderer: O2) -> HeuristicAlphaBeta<G, O2> {
+    HeuristicAlphaBeta {
+      orderer,
+      _game: PhantomData,
+    }
+  }
+
+  /// Searches `game` to `depth`, returning its score, its evaluation, and the
+  /// move with the best evaluation.
+  pub fn best_move_evaluated(
+    &mut self,
+    game: &G,
+    depth: u32,
+  ) -> (Score, Evaluation, Option<G::Move>) {
+    debug_assert!(matches!(game.finished(), GameResult::NotFinished));
+    if depth == 0 {
+      return (Score::NO_INFO, Evaluation::heuristic(game.evaluate()), None);
+    }
+
+    let mut acc = Score::lose(1);
+    let mut best: Option<(Evaluation, G::Move)> = None;
+    for m in self.moves(game, depth) {
+      let alpha = best.map_or(i32::MIN, |(evaluation, _)| evaluation.value());
+      let (score, evaluation) = self.child_score(&game.with_move(m), depth - 1, alpha, i32::MAX);
+      acc = acc.accumulate(score);
+      if best.is_none_or(|(best_evaluation, _)| evaluation > best_evaluation) {
+        best = Some((evaluation, m));
+      }
+    }
+
+    match best {
+      Some((evaluation, m)) => (acc, evaluation, Some(m)),
+      // If you can't make a move, you lose.
+      None => (acc, Evaluation::new(acc, 0), None),
+    }
+  }
+
+  fn moves(&self, game: &G, depth: u32) -> Vec<G::Move> {
+    let mut moves: Vec<_> = game.each_move().collect();
+    self.orderer.order_moves(game, depth, None, &mut moves);
+    moves
+  }
+
+  /// Returns the score and evaluation of `game` from the perspective of its
+  /// parent, which only needs to know them exactly if the evaluation lies
+  /// within (`alpha`, `beta`).
+  fn child_score(&self, game: &G, depth: u32, alpha: i32, beta: i32) -> (Score, Evaluation) {
+    let score = match game.finished() {
+      GameResult::Win(player) => {
+        if player == game.current_player() {
+          Score::lose(1)
+        } else {
+          Score::win(1)
+        }
+      }
+      GameResult::Tie => Score::guaranteed_tie(),
+      GameResult::NotFinished => {
+        let (score, evaluation) =
+          self.search(game, depth, beta.saturating_neg(), alpha.saturating_neg());
+        return (score.backstep(), evaluation.backstep());
+      }
+    };
+    (score, Evaluation::new(score, 0))
+  }
+
+  /// Returns the score and evaluation of `game`. If the evaluation is outside
+  /// of (`alpha`, `beta`), only a bound on it is returned, and the score only
+  /// keeps what the children explored before the cutoff prove.
+  fn search(&self, game: &G, depth: u32, mut alpha: i32, beta: i32) -> (Score, Evaluation) {
+    debug_assert!(matches!(game.finished(), GameResult::NotFinished));
+    if depth == 0 {
+      return (Score::NO_INFO, Evaluation::heuristic(game.evaluate()));
+    }
+
+    let mut acc = Score::lose(1);
+    let mut best: Option<Evaluation> = None;
+    for m in self.moves(game, depth) {
+      let (score, evaluation) = self.child_score(&game.with_move(m), depth - 1, alpha, beta);
+      acc = acc.accumulate(score);
+      best = best.max(Some(evaluation));
+      if evaluation.value() >= beta {
+        self.orderer.record_cutoff(game, depth, m);
+        return (acc.break_early(), evaluation);
+      }
+      alpha = alpha.max(evaluation.value());
+    }
+
+    (acc, best.unwrap_or_else(|| Evaluation::new(acc, 0)))
+  }
+}
+
+impl<G: Evaluate, O: MoveOrderer<G>> Solver for HeuristicAlphaBeta<G, O> {
+  type Game = G;
+
+  fn best_move(&mut self, game: &G, depth: u32) -> (Score, Option<G::Move>) {
+    let (score, _, best_move) = self.best_move_evaluated(game, depth);
+    (score, best_move)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use abstract_game::{
+    test_util::deterministic_random_unfinished_state, Game, GameResult, ScoreValue, Solver,
+  };
+  use rand::{rngs::StdRng, Rng, SeedableRng};
+
+  use googletest::{gtest, prelude::*};
+
+  use crate::{
+    move_order::HeuristicOrdering,
+    solvers::simple::SimpleSolver,
+    test::gomoku::{Gomoku, GomokuMove},
+  };
+
+  use super::HeuristicAlphaBeta;
+
+  fn play(moves: &[(u32, u32)]) -> Gomoku {
+    moves.iter().fold(Gomoku::new(5, 5, 4), |gomoku, &(x, y)| {
+      gomoku.with_move(GomokuMove::new(x, y))
+    })
+  }
+
+  #[gtest]
+  fn test_takes_win() {
+    // X has three in a row, and can win on either end.
+    let gomoku = play(&[(1, 0), (1, 4), (2, 0), (2, 4), (3, 0), (4, 4)]);
+    let (score, evaluation, best_move) = HeuristicAlphaBeta::new().best_move_evaluated(&gomoku, 3);
+    expect_eq!(score.score(), ScoreValue::CurrentPlayerWins);
+    expect_eq!(evaluation.to_string(), "win in 1");
+    expect_eq!(
+      gomoku.with_move(best_move.unwrap()).finished(),
+      GameResult::Win(gomoku.current_player())
+    );
+  }
+
+  #[gtest]
+  fn test_blocks_loss() {
+    // O threatens to win at (4, 1), which X must block.
+    let gomoku = play(&[(0, 1), (1, 1), (4, 4), (2, 1), (0, 4), (3, 1)]);
+    let (_, evaluation, best_move) = HeuristicAlphaBeta::new()
+      .with_move_orderer(HeuristicOrdering)
+      .best_move_evaluated(&gomoku, 2);
+    expect_false!(evaluation.is_proven());
+    expect_that!(best_move, some(eq(GomokuMove::new(4, 1))));
+  }
+
+  #[gtest]
+  fn test_score_matches_simple_solver() {
+    let mut rng = StdRng::seed_from_u64(0x2f1bd7a4c9e0);
+    let mut solver = HeuristicAlphaBeta::new().with_move_orderer(HeuristicOrdering);
+    for _ in 0..200 {
+      let mut gomoku = Gomoku::new(4, 3, 3);
+      let n_moves = rng.random_range(2..=6);
+      deterministic_random_unfinished_state(&mut gomoku, n_moves, &mut rng).unwrap();
+      let depth = rng.random_range(1..=6);
+
+      let (score, evaluation, _) = solver.best_move_evaluated(&gomoku, depth);
+      let (expected_score, _) = SimpleSolver::new().best_move(&gomoku, depth);
+      expect_true!(
+        score.compatible(expected_score),
+        "{score} vs {expected_score} for\n{gomoku}"
+      );
+      if evaluation.is_proven() {
+        expect_ne!(
+          expected_score.score(),
+          ScoreValue::Tie,
+          "{evaluation} for\n{gomoku}"
+        );
+      }
+    }
+  }
+}
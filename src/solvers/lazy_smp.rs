@@ -0,0 +1,236 @@
+use std::{
+  hash::{BuildHasher, Hash, RandomState},
+  sync::atomic::{AtomicBool, Ordering},
+  thread,
+};
+
+use abstract_game::{Game, GameResult, Score, ScoreValue, Solver};
+use rand::{rng, seq::SliceRandom};
+
+use crate::table::Table;
+
+/// A parallel solver in the style of Lazy SMP. Every thread runs the same
+/// iterative deepening alpha-beta search from the root against one shared
+/// transposition table, and the helper threads explore moves in a random order
+/// so they tend to resolve different parts of the tree first. The main thread
+/// searches moves in order and reports its result, stopping the helpers once
+/// it finishes.
+pub struct LazySmp<G, S> {
+  num_threads: u32,
+  table: Table<G, S>,
+}
+
+impl<G: Game + Hash + Eq> LazySmp<G, RandomState> {
+  pub fn new(num_threads: u32) -> Self {
+    Self::with_hasher(num_threads, RandomState::new())
+  }
+}
+
+impl<G: Game + Hash + Eq, S: BuildHasher + Clone> LazySmp<G, S> {
+  pub fn with_hasher(num_threads: u32, hasher: S) -> Self {
+    debug_assert!(num_threads > 0);
+    Self {
+      num_threads,
+      table: Table::with_hasher(hasher),
+    }
+  }
+
+  pub fn num_threads(&self) -> u32 {
+    self.num_threads
+  }
+}
+
+/// The search run by each thread.
+struct SearchThread<'a, G, S> {
+  table: &'a Table<G, S>,
+  /// Set once the main thread has finished, after which helpers give up.
+  stop: &'a AtomicBool,
+  /// Whether to shuffle the moves of each state before searching them.
+  shuffle_moves: bool,
+}
+
+impl<G: Game + Hash + Eq, S: BuildHasher + Clone> SearchThread<'_, G, S> {
+  /// Returns the score of `game` from the perspective of its parent, or `None`
+  /// if the search was stopped before it was found.
+  fn backstepped_score_for_game(
+    &self,
+    game: &G,
+    depth: u32,
+    alpha: ScoreValue,
+    beta: ScoreValue,
+  ) -> Option<Score> {
+    match game.finished() {
+      GameResult::Win(player) => {
+        if player == game.current_player() {
+          return Some(Score::lose(1));
+        } else {
+          return Some(Score::win(1));
+        }
+      }
+      GameResult::Tie => return Some(Score::guaranteed_tie()),
+      GameResult::NotFinished => {}
+    }
+
+    let known_score = self.table.get(game);
+    if let Some(score) = known_score {
+      if score.determined(depth) {
+        return Some(score.backstep());
+      }
+    }
+
+    let new_alpha = beta.invert();
+    let new_beta = alpha.invert();
+    let score = self.solve_impl(game, depth, new_alpha, new_beta)?;
+    self.table.update(game.clone(), score);
+    Some(
+      known_score
+        .map_or(score, |known_score| known_score.merge(score))
+        .backstep(),
+    )
+  }
+
+  fn solve_impl(&self, game: &G, depth: u32, alpha: ScoreValue, beta: ScoreValue) -> Option<Score> {
+    debug_assert!(matches!(game.finished(), GameResult::NotFinished));
+    debug_assert!(alpha <= beta, "{alpha} vs {beta}");
+    if depth == 0 {
+      return Some(Score::NO_INFO);
+    }
+    // Scores computed from only some of the children would be wrong, so a
+    // stopped search commits nothing.
+    if self.stop.load(Ordering::Relaxed) {
+      return None;
+    }
+
+    let mut acc = Score::lose(1);
+    for next_game in self.children(game) {
+      let score =
+        self.backstepped_score_for_game(&next_game, depth - 1, alpha.max(acc.score()), beta)?;
+      acc = acc.accumulate(score);
+      if score.score() >= beta {
+        return Some(acc.break_early());
+      }
+    }
+
+    Some(acc)
+  }
+
+  /// Searches each move from `game` to `depth`, returning the score of `game`
+  /// along with the best move, or `None` if the search was stopped.
+  fn search_root(&self, game: &G, depth: u32) -> Option<(Score, Option<G::Move>)> {
+    // Moves after the first are searched with narrowed windows, so their
+    // scores are only bounds. The score of `game` must be accumulated the same
+    // way as every other state, rather than taken from the best of them.
+    let mut acc = Score::lose(1);
+    let mut best = None;
+    for m in self.moves(game) {
+      let score = self.backstepped_score_for_game(
+        &game.with_move(m),
+        depth - 1,
+        acc.score(),
+        ScoreValue::CurrentPlayerWins,
+      )?;
+      acc = acc.accumulate(score);
+      if best.is_none_or(|(best_score, _)| score > best_score) {
+        best = Some((score, m));
+      }
+    }
+    Some((acc, best.map(|(_, m)| m)))
+  }
+
+  fn moves(&self, game: &G) -> Vec<G::Move> {
+    let mut moves: Vec<_> = game.each_move().collect();
+    if self.shuffle_moves {
+      moves.shuffle(&mut rng());
+    }
+    moves
+  }
+
+  fn children(&self, game: &G) -> impl Iterator<Item = G> + '_ {
+    let game = game.clone();
+    self
+      .moves(&game)
+      .into_iter()
+      .map(move |m| game.with_move(m))
+  }
+}
+
+impl<G, S> Solver for LazySmp<G, S>
+where
+  G: Game + Hash + Eq + Send + Sync,
+  G::Move: Send,
+  S: BuildHasher + Clone + Send + Sync,
+{
+  type Game = G;
+
+  fn best_move(&mut self, game: &G, depth: u32) -> (Score, Option<G::Move>) {
+    debug_assert!(matches!(game.finished(), GameResult::NotFinished));
+    if depth == 0 {
+      return (Score::NO_INFO, None);
+    }
+
+    let stop = AtomicBool::new(false);
+    let search_thread = |shuffle_moves| SearchThread {
+      table: &self.table,
+      stop: &stop,
+      shuffle_moves,
+    };
+
+    thread::scope(|s| {
+      for _ in 1..self.num_threads {
+        let helper = search_thread(true);
+        s.spawn(move || {
+          for depth in 1..=depth {
+            if helper.search_root(game, depth).is_none() {
+              break;
+            }
+          }
+        });
+      }
+
+      let main = search_thread(false);
+      let mut result = (Score::NO_INFO, None);
+      for depth in 1..=depth {
+        result = main
+          .search_root(game, depth)
+          .expect("The main thread is never stopped.");
+      }
+      stop.store(true, Ordering::Relaxed);
+      result
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use abstract_game::{test_games::Nim, ScoreValue, Solver};
+
+  use googletest::{gtest, prelude::*};
+
+  use super::LazySmp;
+
+  #[gtest]
+  fn test_solve_nim() {
+    for sticks in 1..=20 {
+      let depth = sticks + 1;
+      let expected_winner = sticks % 3 != 0;
+
+      let mut solver = LazySmp::new(4);
+      let (score, best_move) = solver.best_move(&Nim::new(sticks), sticks + 1);
+
+      expect_eq!(
+        score.score_at_depth(depth),
+        if expected_winner {
+          ScoreValue::CurrentPlayerWins
+        } else {
+          ScoreValue::OtherPlayerWins
+        },
+        "Game with {sticks} sticks"
+      );
+      if expected_winner {
+        expect_that!(best_move, some(eq(sticks % 3)));
+      } else {
+        expect_that!(best_move, some(anything()));
+      }
+    }
+  }
+}
@@ -1,5 +1,7 @@
 pub mod alpha_beta;
+pub mod heuristic;
 pub mod iter_deep;
+pub mod lazy_smp;
 pub mod simple;
 pub mod ttable_alpha_beta;
 pub mod ttable_solver;
@@ -4,6 +4,12 @@ use abstract_game::{complete_solver::CompleteSolver, Game, GameResult, Score, So
 
 pub struct SimpleSolver<G>(PhantomData<G>);
 
+impl<G: Game> Default for SimpleSolver<G> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl<G: Game> SimpleSolver<G> {
   pub fn new() -> Self {
     Self(PhantomData)
@@ -55,7 +61,7 @@ impl<G: Game> Solver for SimpleSolver<G> {
         let score = Self::score_for_game(&next_game, depth);
         (score, Some(m))
       })
-      .max_by_key(|(score, _)| score.clone())
+      .max_by_key(|(score, _)| *score)
       // If you can't make a move, you lose.
       .unwrap_or((Score::lose(1), None))
   }
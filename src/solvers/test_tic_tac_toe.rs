@@ -2,7 +2,7 @@ use abstract_game::{
   complete_solver::CompleteSolver,
   determined_score::DeterminedScore,
   test_games::{TTTMove, TicTacToe},
-  Game, Solver,
+  Game,
 };
 
 use googletest::{gtest, prelude::*};
@@ -18,7 +18,7 @@ fn complete_solvers(#[values(SimpleSolver::new(), TTSolver::new())] solver: (imp
 
 #[apply(complete_solvers)]
 #[gtest]
-fn test_solve(mut solver: impl CompleteSolver + Solver<Game = TicTacToe>) {
+fn test_solve(mut solver: impl CompleteSolver<Game = TicTacToe>) {
   let mut ttt = TicTacToe::new();
   {
     let (score, m) = solver.best_move_determined(&ttt, 9);
@@ -1,18 +1,38 @@
 use std::{
   collections::{hash_map::Entry, HashMap},
   hash::{BuildHasher, Hash, RandomState},
+  io::{self, Read},
 };
 
 use abstract_game::{Game, GameResult, Score, ScoreValue, Solver};
 
-pub struct TTAlphaBeta<G, S> {
+use crate::{
+  canonicalize::{CanonicalTable, Canonicalize, Canonicalizer},
+  immediate_win::{has_immediate_win, ImmediateWin},
+  move_order::{MoveOrderer, NoOrdering},
+  persist::{merge_entry, read_table, EncodeGame},
+};
+
+pub struct TTAlphaBeta<G, S, O = NoOrdering> {
   table: HashMap<G, Score, S>,
+  canonicalizer: Canonicalizer<G>,
+  orderer: O,
+  immediate_win_check: Option<fn(&G) -> bool>,
+}
+
+impl<G> Default for TTAlphaBeta<G, RandomState> {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl<G> TTAlphaBeta<G, RandomState> {
   pub fn new() -> Self {
     Self {
       table: HashMap::new(),
+      canonicalizer: Canonicalizer::identity(),
+      orderer: NoOrdering,
+      immediate_win_check: None,
     }
   }
 }
@@ -21,6 +41,45 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTAlphaBeta<G, S> {
   pub fn with_hasher(hasher: S) -> Self {
     Self {
       table: HashMap::with_hasher(hasher),
+      canonicalizer: Canonicalizer::identity(),
+      orderer: NoOrdering,
+      immediate_win_check: None,
+    }
+  }
+}
+
+impl<G: Game + Hash + Eq, S: BuildHasher + Clone, O: MoveOrderer<G>> TTAlphaBeta<G, S, O> {
+  /// Makes the solver search moves in the order chosen by `orderer`.
+  pub fn with_move_orderer<O2: MoveOrderer<G>>(self, orderer: O2) -> TTAlphaBeta<G, S, O2> {
+    TTAlphaBeta {
+      table: self.table,
+      canonicalizer: self.canonicalizer,
+      orderer,
+      immediate_win_check: self.immediate_win_check,
+    }
+  }
+
+  /// Makes the solver resolve states where the player to move has an immediate
+  /// win to a win in one, without searching their moves.
+  pub fn with_immediate_wins(self) -> Self
+  where
+    G: ImmediateWin,
+  {
+    Self {
+      immediate_win_check: Some(has_immediate_win::<G>),
+      ..self
+    }
+  }
+
+  /// Makes the solver store symmetric states under a single entry of its table.
+  pub fn with_canonicalization(self) -> Self
+  where
+    G: Canonicalize,
+  {
+    debug_assert!(self.table.is_empty());
+    Self {
+      canonicalizer: Canonicalizer::new(),
+      ..self
     }
   }
 
@@ -28,6 +87,18 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTAlphaBeta<G, S> {
     &self.table
   }
 
+  /// Merges the entries of a saved table into this solver's table, returning
+  /// the search depth the table was saved with.
+  pub fn load_table<R: Read>(&mut self, reader: R) -> io::Result<u32>
+  where
+    G: EncodeGame,
+  {
+    let canonicalizer = self.canonicalizer;
+    read_table(reader, |game, score| {
+      merge_entry(&mut self.table, canonicalizer.owned_key(game), score)
+    })
+  }
+
   fn backstepped_score_for_game(
     &mut self,
     game: &G,
@@ -47,7 +118,7 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTAlphaBeta<G, S> {
       GameResult::NotFinished => {}
     }
 
-    if let Some(&score) = self.table.get(game) {
+    if let Some(&score) = self.table.get(&self.canonicalizer.key(game)) {
       if score.determined(depth) {
         return score.backstep();
       }
@@ -57,7 +128,7 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTAlphaBeta<G, S> {
     let new_beta = alpha.invert();
     let score = self.solve_impl(game, depth, new_alpha, new_beta);
 
-    match self.table.entry(game.clone()) {
+    match self.table.entry(self.canonicalizer.key(game).into_owned()) {
       Entry::Occupied(mut entry) => {
         let merged = entry.get().merge(score);
         *entry.get_mut() = merged;
@@ -71,19 +142,41 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTAlphaBeta<G, S> {
     .backstep()
   }
 
+  /// Returns the moves of `game` in the order they should be searched to
+  /// `depth`.
+  fn moves(&self, game: &G, depth: u32) -> Vec<G::Move> {
+    let mut moves: Vec<_> = game.each_move().collect();
+    let table = CanonicalTable {
+      table: &self.table,
+      canonicalizer: self.canonicalizer,
+    };
+    self
+      .orderer
+      .order_moves(game, depth, Some(&table), &mut moves);
+    moves
+  }
+
   fn solve_impl(&mut self, game: &G, depth: u32, alpha: ScoreValue, beta: ScoreValue) -> Score {
     debug_assert!(matches!(game.finished(), GameResult::NotFinished));
     debug_assert!(alpha <= beta, "{alpha} vs {beta}");
     if depth == 0 {
       return Score::NO_INFO;
     }
+    if self
+      .immediate_win_check
+      .is_some_and(|immediate_win_check| immediate_win_check(game))
+    {
+      return Score::win(1);
+    }
 
     let mut acc = Score::lose(1);
-    for next_game in game.each_move().map(|m| game.with_move(m)) {
+    for m in self.moves(game, depth) {
+      let next_game = game.with_move(m);
       let score =
         self.backstepped_score_for_game(&next_game, depth - 1, alpha.max(acc.score()), beta);
       acc = acc.accumulate(score);
       if score.score() >= beta {
+        self.orderer.record_cutoff(game, depth, m);
         return acc.break_early();
       }
     }
@@ -92,7 +185,12 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTAlphaBeta<G, S> {
   }
 }
 
-impl<G: Game + Hash + Eq, S: BuildHasher + Clone> Solver for TTAlphaBeta<G, S> {
+impl<G, S, O> Solver for TTAlphaBeta<G, S, O>
+where
+  G: Game + Hash + Eq,
+  S: BuildHasher + Clone,
+  O: MoveOrderer<G>,
+{
   type Game = G;
 
   fn best_move(&mut self, game: &G, depth: u32) -> (Score, Option<G::Move>) {
@@ -101,24 +199,23 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> Solver for TTAlphaBeta<G, S> {
       return (Score::NO_INFO, None);
     }
 
-    let mut alpha = ScoreValue::OtherPlayerWins;
-
-    game
-      .each_move()
-      .map(|m| {
-        let next_game = game.with_move(m);
-        let score = self.backstepped_score_for_game(
-          &next_game,
-          depth - 1,
-          alpha,
-          ScoreValue::CurrentPlayerWins,
-        );
-        alpha = alpha.max(score.score());
-        (score, Some(m))
-      })
-      .max_by_key(|(score, _)| score.clone())
-      // If you can't make a move, you lose.
-      .unwrap_or((Score::lose(1), None))
+    // As in `solve_impl`, later moves only get bounds from their narrowed
+    // windows.
+    let mut acc = Score::lose(1);
+    let mut best = None;
+    for m in self.moves(game, depth) {
+      let score = self.backstepped_score_for_game(
+        &game.with_move(m),
+        depth - 1,
+        acc.score(),
+        ScoreValue::CurrentPlayerWins,
+      );
+      acc = acc.accumulate(score);
+      if best.is_none_or(|(best_score, _)| score > best_score) {
+        best = Some((score, m));
+      }
+    }
+    (acc, best.map(|(_, m)| m))
   }
 }
 
@@ -1,18 +1,32 @@
 use std::{
   collections::{hash_map::Entry, HashMap},
   hash::{BuildHasher, Hash, RandomState},
+  io::{self, Read},
 };
 
 use abstract_game::{complete_solver::CompleteSolver, Game, GameResult, Score, Solver};
 
+use crate::{
+  canonicalize::{Canonicalize, Canonicalizer},
+  persist::{merge_entry, read_table, EncodeGame},
+};
+
 pub struct TTSolver<G, S> {
   table: HashMap<G, Score, S>,
+  canonicalizer: Canonicalizer<G>,
+}
+
+impl<G: Game + Hash + Eq> Default for TTSolver<G, RandomState> {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl<G: Game + Hash + Eq> TTSolver<G, RandomState> {
   pub fn new() -> Self {
     Self {
       table: HashMap::new(),
+      canonicalizer: Canonicalizer::identity(),
     }
   }
 }
@@ -21,6 +35,19 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTSolver<G, S> {
   pub fn with_hasher(hasher: S) -> Self {
     Self {
       table: HashMap::with_hasher(hasher),
+      canonicalizer: Canonicalizer::identity(),
+    }
+  }
+
+  /// Makes the solver store symmetric states under a single entry of its table.
+  pub fn with_canonicalization(self) -> Self
+  where
+    G: Canonicalize,
+  {
+    debug_assert!(self.table.is_empty());
+    Self {
+      canonicalizer: Canonicalizer::new(),
+      ..self
     }
   }
 
@@ -28,6 +55,18 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTSolver<G, S> {
     &self.table
   }
 
+  /// Merges the entries of a saved table into this solver's table, returning
+  /// the search depth the table was saved with.
+  pub fn load_table<R: Read>(&mut self, reader: R) -> io::Result<u32>
+  where
+    G: EncodeGame,
+  {
+    let canonicalizer = self.canonicalizer;
+    read_table(reader, |game, score| {
+      merge_entry(&mut self.table, canonicalizer.owned_key(game), score)
+    })
+  }
+
   fn backstepped_score_for_game(&mut self, game: &G, depth: u32) -> Score {
     match game.finished() {
       GameResult::Win(player) => {
@@ -41,7 +80,7 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTSolver<G, S> {
       GameResult::NotFinished => {}
     }
 
-    if let Some(&score) = self.table.get(game) {
+    if let Some(&score) = self.table.get(&self.canonicalizer.key(game)) {
       if score.determined(depth) {
         return score.backstep();
       }
@@ -49,7 +88,7 @@ impl<G: Game + Hash + Eq, S: BuildHasher + Clone> TTSolver<G, S> {
 
     let score = self.solve_impl(game, depth);
 
-    match self.table.entry(game.clone()) {
+    match self.table.entry(self.canonicalizer.key(game).into_owned()) {
       Entry::Occupied(mut entry) => {
         let merged = entry.get().merge(score);
         *entry.get_mut() = merged;
@@ -93,7 +132,7 @@ impl<G: Game + Hash + Eq, H: BuildHasher + Clone> Solver for TTSolver<G, H> {
           Some(m),
         )
       })
-      .max_by_key(|(score, _)| score.clone())
+      .max_by_key(|(score, _)| *score)
       // If you can't make a move, you lose.
       .unwrap_or((Score::lose(1), None))
   }
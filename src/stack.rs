@@ -5,9 +5,9 @@ use std::{
   sync::atomic::{AtomicU32, Ordering},
 };
 
-use abstract_game::{Game, Score};
+use abstract_game::{Game, GameResult, Score, ScoreValue};
 
-use crate::transparent_iterator::TransparentIterator;
+use crate::evaluate::Evaluation;
 
 /// Algorithm:
 /// ```rs
@@ -83,21 +83,70 @@ pub enum StackState {
   Suspended,
 }
 
+/// The bounds on the score of a frame, from the perspective of the player to
+/// move, outside of which its exact score can't affect the score of the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Window {
+  alpha: ScoreValue,
+  beta: ScoreValue,
+}
+
+impl Window {
+  /// The window of a frame whose score can be anything.
+  pub const FULL: Self = Self {
+    alpha: ScoreValue::OtherPlayerWins,
+    beta: ScoreValue::CurrentPlayerWins,
+  };
+
+  /// The window to search a child of a frame with this window with, where
+  /// `best_score` is the score accumulated from the frame's children so far.
+  fn child(&self, best_score: Score) -> Self {
+    let alpha = self.alpha.max(best_score.score());
+    debug_assert!(alpha <= self.beta, "{alpha} vs {}", self.beta);
+    Self {
+      alpha: self.beta.invert(),
+      beta: alpha.invert(),
+    }
+  }
+}
+
+/// Where the moves of a frame come from.
+enum FrameMoves<G>
+where
+  G: Game,
+{
+  /// An iterator over the moves at this game state. If `None`, then no moves
+  /// have been iterated over yet.
+  Generated(Option<G::MoveGenerator>),
+  /// Every move of this game state, in the order chosen by a move orderer, and
+  /// the index of the next one to explore.
+  Ordered { moves: Vec<G::Move>, next: usize },
+}
+
 pub struct StackFrame<G>
 where
   G: Game,
 {
   /// The current game state at this frame.
   game: G,
-  /// An iterator over the moves at this game state. If `None`, then no moves
-  /// have been iterated over yet.
-  move_gen: Option<G::MoveGenerator>,
+  /// The moves at this game state.
+  moves: FrameMoves<G>,
   /// The current move being explored by the child of this frame.
   current_move: Option<G::Move>,
-  /// The best score found for this game so far.
+  /// The score of this game accumulated from every child explored so far.
   best_score: Score,
-  /// The corresponding best move found for `best_score`.
+  /// The best move found so far.
   best_move: Option<G::Move>,
+  /// The score of the child reached by `best_move`.
+  best_move_score: Score,
+  /// The best evaluation of any child explored so far, from the perspective of
+  /// the player to move in this frame.
+  evaluation: Evaluation,
+  /// If set, the remaining moves of this frame are cut off as soon as a child
+  /// scores at least `beta`. Otherwise, every move is explored.
+  window: Option<Window>,
+  /// True if some moves of this frame were cut off.
+  cut_off: bool,
   /// All stack frames have an unordered list of all of their suspended direct
   /// dependents. This can only be appended to under the bin mutex lock from the
   /// pending states hashmap, and reclaimed for revival after removing this
@@ -110,13 +159,19 @@ where
   G: Game + Display,
   G::Move: Display,
 {
-  pub fn new(game: G) -> Self {
+  pub fn new(game: G, window: Option<Window>) -> Self {
     let mut s = Self {
       game,
-      move_gen: None,
+      moves: FrameMoves::Generated(None),
       current_move: None,
-      best_score: Score::NO_INFO,
+      // If there are no possible moves, then the game is considered lost for
+      // the current player.
+      best_score: Score::lose(1),
       best_move: None,
+      best_move_score: Score::NO_INFO,
+      evaluation: Evaluation::new(Score::lose(1), 0),
+      window,
+      cut_off: false,
       dependents: null_mut(),
     };
     s.advance();
@@ -132,25 +187,56 @@ where
     self.current_move
   }
 
+  /// True if some moves of this frame were cut off, in which case its score
+  /// may be missing information a full search would have found.
+  pub fn cut_off(&self) -> bool {
+    self.cut_off
+  }
+
+  /// Replaces the moves of this frame with `moves`, which must be every move
+  /// of its game state, and starts exploring them from the first. This may only
+  /// be called before any of the frame's children have been explored.
+  pub fn set_move_order(&mut self, moves: Vec<G::Move>) {
+    debug_assert!(self.best_move.is_none());
+    self.moves = FrameMoves::Ordered { moves, next: 0 };
+    self.advance();
+  }
+
+  /// The window to search the child reached by the current move with.
+  fn child_window(&self) -> Option<Window> {
+    self.window.map(|window| window.child(self.best_score))
+  }
+
   pub fn best_score(&self) -> (Score, Option<G::Move>) {
     // The state should have been fully explored.
     debug_assert!(self.current_move.is_none());
-    (
-      if self.best_move.is_none() {
-        // If there were no possible moves, then the game is considered lost for
-        // the current player.
-        Score::lose(1)
-      } else {
-        self.best_score.clone()
-      },
-      self.best_move,
-    )
-  }
-
-  /// Updates the best score/move pair of this frame if `score` is better than
-  /// the current best score, and advances the current move to the next move.
-  fn update_score_and_advance(&mut self, score: Score) {
-    if self.best_move.is_none() || score.better(self.best_score) {
+    (self.best_score, self.best_move)
+  }
+
+  /// The evaluation of this frame, which is only complete once the frame has
+  /// been fully explored.
+  pub fn evaluation(&self) -> Evaluation {
+    self.evaluation
+  }
+
+  /// Accumulates `score` into the score of this frame, updating the best move
+  /// if `score` is better than that of the current best move, and advances the
+  /// current move to the next move.
+  ///
+  /// Scores must be accumulated rather than only keeping the best, since the
+  /// children may be determined to different depths. A child which is a tie
+  /// forever is not better than a child which is a tie for now, but may turn
+  /// out to be a win when searched deeper.
+  ///
+  /// If `score` closes this frame's window, the remaining moves are cut off
+  /// and only what the explored children prove is kept.
+  ///
+  /// `evaluation` is the evaluation of the child from the perspective of this
+  /// frame, and the frame keeps the best of them.
+  fn update_score_and_advance(&mut self, score: Score, evaluation: Evaluation) {
+    self.best_score = self.best_score.accumulate(score);
+    self.evaluation = self.evaluation.max(evaluation);
+    if self.best_move.is_none() || score.better(self.best_move_score) {
       // println!(
       //   "    Updating {} ({}) to {} ({}) for\n{}\n",
       //   if self.best_move.is_none() {
@@ -163,7 +249,7 @@ where
       //   score,
       //   self.game()
       // );
-      self.best_score = score;
+      self.best_move_score = score;
       self.best_move = self.current_move;
     } else {
       // println!(
@@ -175,6 +261,40 @@ where
       //   self.game()
       // );
     }
+    if self
+      .window
+      .is_some_and(|window| score.score() >= window.beta)
+    {
+      self.best_score = self.best_score.break_early();
+      self.current_move = None;
+      self.cut_off = true;
+      return;
+    }
+    self.advance();
+  }
+
+  /// Resolves this frame to `score`/`best_move`/`evaluation` without exploring
+  /// any more of its moves. This is used for frames whose children were
+  /// explored separately, i.e. by split child stacks.
+  pub fn resolve(&mut self, score: Score, best_move: Option<G::Move>, evaluation: Evaluation) {
+    self.best_score = score;
+    self.best_move = best_move;
+    self.evaluation = evaluation;
+    self.current_move = None;
+  }
+
+  /// Discards everything learned about this frame's children and starts
+  /// exploring them again from the first move. Suspended dependants are kept.
+  pub fn restart(&mut self) {
+    match &mut self.moves {
+      FrameMoves::Generated(move_gen) => *move_gen = None,
+      FrameMoves::Ordered { next, .. } => *next = 0,
+    }
+    self.best_score = Score::lose(1);
+    self.best_move = None;
+    self.best_move_score = Score::NO_INFO;
+    self.evaluation = Evaluation::new(Score::lose(1), 0);
+    self.cut_off = false;
     self.advance();
   }
 
@@ -197,14 +317,16 @@ where
 
   /// Advances the current move to the next possible move.
   fn advance(&mut self) {
-    let move_gen = match &mut self.move_gen {
-      Some(move_gen) => move_gen,
-      None => {
-        self.move_gen = Some(self.game.move_generator());
-        self.move_gen.as_mut().unwrap()
+    self.current_move = match &mut self.moves {
+      FrameMoves::Generated(move_gen) => move_gen
+        .get_or_insert_with(|| self.game.move_generator())
+        .next(&self.game),
+      FrameMoves::Ordered { moves, next } => {
+        let m = moves.get(*next).copied();
+        *next += 1;
+        m
       }
     };
-    self.current_move = move_gen.next(&self.game);
   }
 }
 
@@ -251,11 +373,20 @@ where
       next: null_mut(),
       outstanding_children: AtomicU32::new(0),
     };
-    root.frames.push(StackFrame::new(initial_game));
+    root.frames.push(StackFrame::new(initial_game, None));
     root
   }
 
-  fn make_child(game: G, depth: u32, parent: *mut Self) -> Self {
+  /// Makes the stack prune moves with alpha-beta windows, starting from a full
+  /// window at the root frame. Every frame pushed onto the stack, and every
+  /// child stack split from it, inherits a window from its parent.
+  pub fn with_alpha_beta(mut self) -> Self {
+    debug_assert_eq!(self.frames.len(), 1);
+    self.frames[0].window = Some(Window::FULL);
+    self
+  }
+
+  fn make_child(game: G, depth: u32, window: Option<Window>, parent: *mut Self) -> Self {
     let mut root = Self {
       root_depth: depth,
       frames: Vec::with_capacity(depth as usize),
@@ -264,7 +395,7 @@ where
       next: null_mut(),
       outstanding_children: AtomicU32::new(0),
     };
-    root.frames.push(StackFrame::new(game));
+    root.frames.push(StackFrame::new(game, window));
     root
   }
 
@@ -272,31 +403,38 @@ where
     &self.ty
   }
 
+  /// Pushes the state reached by the current move of the bottom frame.
   pub fn push(&mut self, game: G) {
     debug_assert!(!self.is_full());
-    self.frames.push(StackFrame::new(game));
+    let window = self.bottom_frame().and_then(|frame| frame.child_window());
+    self.frames.push(StackFrame::new(game, window));
   }
 
-  pub fn update_parent_score_and_advance(&mut self, score: Score) {
+  pub fn update_parent_score_and_advance(&mut self, score: Score, evaluation: Evaluation) {
     if let Some(parent_frame) = self.frames.last_mut() {
-      parent_frame.update_score_and_advance(score);
+      parent_frame.update_score_and_advance(score, evaluation);
     }
   }
 
-  /// To be called to resolve the bottom frame to the given score which is
-  /// already relative to the parent frame. This will remove the bottom stack
-  /// frame and update the score/current move of the parent stack frame.
-  pub fn pop_with_backstepped_score(&mut self, score: Score) -> StackFrame<G> {
+  /// To be called to resolve the bottom frame to the given score and
+  /// evaluation which are already relative to the parent frame. This will
+  /// remove the bottom stack frame and update the score/current move of the
+  /// parent stack frame.
+  pub fn pop_with_backstepped_score(
+    &mut self,
+    score: Score,
+    evaluation: Evaluation,
+  ) -> StackFrame<G> {
     let completed_frame = self.frames.pop().unwrap();
-    self.update_parent_score_and_advance(score);
+    self.update_parent_score_and_advance(score, evaluation);
     completed_frame
   }
 
-  /// To be called to resolve the bottom frame to the given score. This will
-  /// remove the bottom stack frame and update the score/current move of the
-  /// parent stack frame.
-  pub fn pop_with_score(&mut self, score: Score) -> StackFrame<G> {
-    self.pop_with_backstepped_score(score.backstep())
+  /// To be called to resolve the bottom frame to the given score and
+  /// evaluation. This will remove the bottom stack frame and update the
+  /// score/current move of the parent stack frame.
+  pub fn pop_with_score(&mut self, score: Score, evaluation: Evaluation) -> StackFrame<G> {
+    self.pop_with_backstepped_score(score.backstep(), evaluation.backstep())
   }
 
   /// To be called when the bottom stack frame has resolved its score. This will
@@ -304,7 +442,12 @@ where
   /// parent stack frame.
   pub fn pop(&mut self) -> StackFrame<G> {
     let completed_frame = self.frames.last().unwrap();
-    self.pop_with_score(completed_frame.best_score().0.clone())
+    self.pop_with_score(completed_frame.best_score().0, completed_frame.evaluation())
+  }
+
+  /// Removes the bottom frame of an abandoned stack without resolving it.
+  pub fn pop_abandoned(&mut self) {
+    self.frames.pop();
   }
 
   pub fn stack_state(&self) -> StackState {
@@ -321,9 +464,15 @@ where
     self.state = StackState::Suspended;
   }
 
-  /// Splits a stack frame into a separate stack frame for each possible move of
-  /// the bottom game state, returning an iterator over the stack frames for
-  /// each child. The iterator must be consumed completely.
+  /// Splits the bottom frame of the stack into a separate child stack for each
+  /// possible move of the bottom game state that doesn't immediately finish
+  /// the game, returning an iterator over the child stacks. The iterator must
+  /// be consumed completely.
+  ///
+  /// This stack holds one extra outstanding child while the children are being
+  /// produced, which the caller must release with `resolve_outstanding_child`
+  /// once it has finished handing out every child. Whoever resolves the last
+  /// outstanding child is responsible for reviving this stack.
   ///
   /// TODO: may want to split at the first frame, not the last.
   pub fn split(self_ptr: *mut Self) -> impl Iterator<Item = Self> {
@@ -338,39 +487,51 @@ where
     // rare, but it's due diligence.
     stack.outstanding_children.store(1, Ordering::Relaxed);
 
-    // Generate the child states of this stack frame.
+    // Generate the child states of this stack frame. None of them have been
+    // explored, so they are all searched with the same window.
+    let window = stack.bottom_frame().unwrap().child_window();
     let game = stack.bottom_frame().unwrap().game();
     game
       .each_move()
       .map(move |m| {
+        unsafe { &*self_ptr }
+          .bottom_frame()
+          .unwrap()
+          .game()
+          .with_move(m)
+      })
+      .filter(|game| game.finished() == GameResult::NotFinished)
+      .map(move |game| {
         let stack = unsafe { &mut *self_ptr };
         stack.outstanding_children.fetch_add(1, Ordering::Relaxed);
-        let mut game = stack.bottom_frame().unwrap().game().clone();
-        game.make_move(m);
-        Self::make_child(game, stack.bottom_depth() - 1, self_ptr)
+        Self::make_child(game, stack.bottom_depth() - 1, window, self_ptr)
       })
-      .chain(TransparentIterator::new(move || {
-        let stack = unsafe { &mut *self_ptr };
-        if stack.outstanding_children.fetch_sub(1, Ordering::Relaxed) == 0 {
-          // TODO: All children have finished, revive this frame.
-        }
-      }))
   }
 
+  /// Marks one of the children of a split stack as finished. Returns true if
+  /// this was the last outstanding child, in which case the caller now has
+  /// exclusive access to the stack and must revive it.
+  ///
   /// TODO: try tracking the best score/move in the parent stack frame, protect
   /// those and outstanding_children with a lock, instead of re-iterating over
   /// the parent and relying on the children states to be in the resolved table.
-  pub fn resolve_outstanding_child(self_ptr: *mut Self) {
-    let stack = unsafe { &mut *self_ptr };
-    if stack.outstanding_children.fetch_sub(1, Ordering::Relaxed) == 0 {
-      // TODO: All children have finished, revive this frame.
-    }
+  pub fn resolve_outstanding_child(self_ptr: *mut Self) -> bool {
+    let stack = unsafe { &*self_ptr };
+    debug_assert_eq!(stack.state, StackState::Split);
+    // The last child to finish reads the scores every other child committed,
+    // so this needs to synchronize with all of them.
+    stack.outstanding_children.fetch_sub(1, Ordering::AcqRel) == 1
   }
 
   pub fn is_full(&self) -> bool {
     self.frames.len() == self.root_depth as usize
   }
 
+  /// The depth the first frame of this stack is searched to.
+  pub fn root_depth(&self) -> u32 {
+    self.root_depth
+  }
+
   pub fn frame(&self, idx: u32) -> &StackFrame<G> {
     self.frames.get(idx as usize).unwrap()
   }
@@ -1,13 +1,211 @@
 use std::{
   collections::hash_map::RandomState,
   hash::{BuildHasher, Hash},
+  io::{self, Read, Write},
 };
 
 use abstract_game::{Game, Score};
 use dashmap::{mapref::entry::Entry, DashMap};
 
+use crate::{
+  bounded_table::{BoundedTable, TableValue},
+  canonicalize::Canonicalizer,
+  evaluate::Evaluation,
+  passthrough_hasher::BuildPassThroughHasher,
+  persist::{read_table, write_table, EncodeGame, PersistTable},
+  principal_variation::ScoreTable,
+};
+
+/// The evaluation of a state, along with the depth it was searched to. Only
+/// the deepest evaluation of each state is kept.
+impl TableValue for (u32, Evaluation) {
+  fn merge(self, new: Self) -> Self {
+    if self.0 <= new.0 {
+      new
+    } else {
+      self
+    }
+  }
+
+  fn depth(&self) -> u32 {
+    self.0
+  }
+}
+
+enum Storage<K, H, V = Score> {
+  /// Keeps every state that is committed to it.
+  Unbounded(DashMap<K, V, H>),
+  /// Keeps a fixed number of states, replacing the least valuable ones.
+  Bounded(BoundedTable<K, H, V>),
+}
+
+impl<K, H, V> Storage<K, H, V>
+where
+  K: Hash + Eq,
+  H: BuildHasher + Clone,
+  V: TableValue,
+{
+  /// Constructs storage which holds at most `capacity` entries, or every entry
+  /// if `capacity` is `None`.
+  fn new(capacity: Option<usize>, hasher: H) -> Self {
+    match capacity {
+      Some(capacity) => Self::Bounded(BoundedTable::with_capacity_and_hasher(capacity, hasher)),
+      None => Self::Unbounded(DashMap::with_hasher(hasher)),
+    }
+  }
+
+  fn hasher(&self) -> &H {
+    match self {
+      Self::Unbounded(table) => table.hasher(),
+      Self::Bounded(table) => table.hasher(),
+    }
+  }
+
+  /// The number of entries this storage can hold, or `None` if it is
+  /// unbounded.
+  fn capacity(&self) -> Option<usize> {
+    match self {
+      Self::Unbounded(_) => None,
+      Self::Bounded(table) => Some(table.capacity()),
+    }
+  }
+
+  /// Constructs empty storage for values of another type, with the same
+  /// capacity and hasher as this storage.
+  fn empty_like<V2: TableValue>(&self) -> Storage<K, H, V2> {
+    Storage::new(self.capacity(), self.hasher().clone())
+  }
+
+  fn get(&self, key: &K) -> Option<V> {
+    match self {
+      Self::Unbounded(table) => table.get(key).map(|entry| *entry.value()),
+      Self::Bounded(table) => table.get(key),
+    }
+  }
+
+  fn update(&self, key: K, value: V) {
+    match self {
+      Self::Unbounded(table) => match table.entry(key) {
+        Entry::Occupied(mut entry) => {
+          entry.insert(entry.get().merge(value));
+        }
+        Entry::Vacant(entry) => {
+          entry.insert(value);
+        }
+      },
+      Self::Bounded(table) => table.update(key, value),
+    }
+  }
+
+  fn new_generation(&self) {
+    if let Self::Bounded(table) = self {
+      table.new_generation();
+    }
+  }
+
+  fn len(&self) -> usize {
+    match self {
+      Self::Unbounded(table) => table.len(),
+      Self::Bounded(table) => table.len(),
+    }
+  }
+
+  fn try_for_each<E>(&self, mut f: impl FnMut(&K, V) -> Result<(), E>) -> Result<(), E> {
+    match self {
+      Self::Unbounded(table) => table
+        .iter()
+        .try_for_each(|entry| f(entry.key(), *entry.value())),
+      Self::Bounded(table) => table.try_for_each(f),
+    }
+  }
+}
+
+enum TableStorage<G, H, V = Score> {
+  /// Stores a copy of each state.
+  States(Storage<G, H, V>),
+  /// Stores only the Zobrist hash of each state, so distinct states with the
+  /// same hash share an entry. `hasher` is kept for the other tables that are
+  /// keyed by the same states.
+  Zobrist {
+    storage: Storage<u64, BuildPassThroughHasher, V>,
+    zobrist_hash: fn(&G) -> u64,
+    hasher: H,
+  },
+}
+
+impl<G, H, V> TableStorage<G, H, V>
+where
+  G: Hash + Eq,
+  H: BuildHasher + Clone,
+  V: TableValue,
+{
+  /// Constructs empty storage for values of another type, keyed the same way
+  /// and with the same capacity as this storage.
+  fn empty_like<V2: TableValue>(&self) -> TableStorage<G, H, V2> {
+    match self {
+      Self::States(storage) => TableStorage::States(storage.empty_like()),
+      Self::Zobrist {
+        storage,
+        zobrist_hash,
+        hasher,
+      } => TableStorage::Zobrist {
+        storage: storage.empty_like(),
+        zobrist_hash: *zobrist_hash,
+        hasher: hasher.clone(),
+      },
+    }
+  }
+
+  /// Looks up the value of `key`, which must already be canonicalized.
+  fn get(&self, key: &G) -> Option<V> {
+    match self {
+      Self::States(storage) => storage.get(key),
+      Self::Zobrist {
+        storage,
+        zobrist_hash,
+        ..
+      } => storage.get(&zobrist_hash(key)),
+    }
+  }
+
+  /// Merges `value` into the entry for `key`, which must already be
+  /// canonicalized.
+  fn update(&self, key: G, value: V) {
+    match self {
+      Self::States(storage) => storage.update(key, value),
+      Self::Zobrist {
+        storage,
+        zobrist_hash,
+        ..
+      } => storage.update(zobrist_hash(&key), value),
+    }
+  }
+
+  fn new_generation(&self) {
+    match self {
+      Self::States(storage) => storage.new_generation(),
+      Self::Zobrist { storage, .. } => storage.new_generation(),
+    }
+  }
+}
+
 pub struct Table<G, H> {
-  table: DashMap<G, Score, H>,
+  storage: TableStorage<G, H>,
+  /// The deepest evaluation committed for each state, if the table is tracking
+  /// evaluations. This is keyed and bounded the same way as `storage`.
+  evaluations: Option<TableStorage<G, H, (u32, Evaluation)>>,
+  /// States are stored under the key this maps them to.
+  canonicalizer: Canonicalizer<G>,
+}
+
+#[cfg(test)]
+impl<G> Default for Table<G, RandomState>
+where
+  G: Game + Hash + Eq,
+{
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl<G> Table<G, RandomState>
@@ -16,9 +214,7 @@ where
 {
   #[cfg(test)]
   pub fn new() -> Self {
-    Self {
-      table: DashMap::new(),
-    }
+    Self::with_hasher(RandomState::new())
   }
 }
 
@@ -28,30 +224,218 @@ where
   H: BuildHasher + Clone,
 {
   pub fn with_hasher(hasher: H) -> Self {
+    Self::from_storage(TableStorage::States(Storage::new(None, hasher)))
+  }
+
+  /// Constructs a table which holds at most `capacity` states (rounded up to
+  /// the bucket size of the table). Once full, committing new states evicts
+  /// old ones.
+  pub fn bounded(capacity: usize, hasher: H) -> Self {
+    Self::from_storage(TableStorage::States(Storage::new(Some(capacity), hasher)))
+  }
+
+  /// Constructs a table which stores the 64-bit `zobrist_hash` of each state in
+  /// place of the state itself. If `capacity` is given, the table is bounded
+  /// like one constructed by `bounded`.
+  pub fn zobrist(capacity: Option<usize>, hasher: H, zobrist_hash: fn(&G) -> u64) -> Self {
+    Self::from_storage(TableStorage::Zobrist {
+      storage: Storage::new(capacity, BuildPassThroughHasher),
+      zobrist_hash,
+      hasher,
+    })
+  }
+
+  fn from_storage(storage: TableStorage<G, H>) -> Self {
+    Self {
+      storage,
+      evaluations: None,
+      canonicalizer: Canonicalizer::identity(),
+    }
+  }
+
+  /// Makes the table store every state under the key `canonicalizer` maps it
+  /// to, so symmetric states share an entry.
+  pub(crate) fn with_canonicalizer(self, canonicalizer: Canonicalizer<G>) -> Self {
     Self {
-      table: DashMap::with_hasher(hasher),
+      canonicalizer,
+      ..self
+    }
+  }
+
+  pub(crate) fn canonicalizer(&self) -> Canonicalizer<G> {
+    self.canonicalizer
+  }
+
+  pub fn hasher(&self) -> &H {
+    match &self.storage {
+      TableStorage::States(storage) => storage.hasher(),
+      TableStorage::Zobrist { hasher, .. } => hasher,
     }
   }
 
   #[cfg(test)]
   pub fn table(&self) -> &DashMap<G, Score, H> {
-    &self.table
+    match &self.storage {
+      TableStorage::States(Storage::Unbounded(table)) => table,
+      _ => panic!("Only unbounded tables of states are backed by a DashMap."),
+    }
+  }
+
+  #[cfg(test)]
+  pub fn entries(&self) -> Vec<(G, Score)> {
+    let TableStorage::States(storage) = &self.storage else {
+      panic!("Tables keyed by Zobrist hashes do not store their states.");
+    };
+    let mut entries = Vec::new();
+    storage
+      .try_for_each(|game, score| {
+        entries.push((game.clone(), score));
+        Ok::<_, ()>(())
+      })
+      .unwrap();
+    entries
   }
 
-  pub fn get(&self, key: &G) -> Option<Score> {
-    self.table.get(key).map(|entry| entry.value().clone())
+  pub fn get(&self, game: &G) -> Option<Score> {
+    self.storage.get(&self.canonicalizer.key(game))
   }
 
   /// Updates an Onoro view in the table, potentially modifying the passed view
   /// to match the merged view that is in the table upon returning.
   pub fn update(&self, state: G, score: Score) {
-    match self.table.entry(state) {
-      Entry::Occupied(mut entry) => {
-        entry.insert(entry.get().merge(score));
-      }
-      Entry::Vacant(entry) => {
-        entry.insert(score);
-      }
+    self
+      .storage
+      .update(self.canonicalizer.owned_key(state), score)
+  }
+
+  /// Makes the table keep the evaluations committed with `update_evaluation`.
+  /// They are bounded and keyed the same way as the scores.
+  pub fn track_evaluations(&mut self) {
+    if self.evaluations.is_none() {
+      self.evaluations = Some(self.storage.empty_like());
+    }
+  }
+
+  /// The deepest evaluation committed for `game`, if the table is tracking
+  /// evaluations.
+  pub fn get_evaluation(&self, game: &G) -> Option<Evaluation> {
+    let evaluations = self.evaluations.as_ref()?;
+    evaluations
+      .get(&self.canonicalizer.key(game))
+      .map(|(_, evaluation)| evaluation)
+  }
+
+  /// Records `evaluation` for `state`, searched to `depth`, unless it was
+  /// already evaluated by a deeper search. Does nothing if the table isn't
+  /// tracking evaluations.
+  pub fn update_evaluation(&self, state: G, depth: u32, evaluation: Evaluation) {
+    if let Some(evaluations) = &self.evaluations {
+      evaluations.update(self.canonicalizer.owned_key(state), (depth, evaluation));
+    }
+  }
+
+  /// Marks the start of a new search. Bounded tables evict states from
+  /// previous searches first.
+  pub fn new_generation(&self) {
+    self.storage.new_generation();
+    if let Some(evaluations) = &self.evaluations {
+      evaluations.new_generation();
     }
   }
 }
+
+impl<G, H> ScoreTable<G> for Table<G, H>
+where
+  G: Game + Hash + Eq,
+  H: BuildHasher + Clone,
+{
+  fn lookup_score(&self, game: &G) -> Option<Score> {
+    self.get(game)
+  }
+}
+
+impl<G, H> PersistTable for Table<G, H>
+where
+  G: EncodeGame + Game + Hash + Eq,
+  H: BuildHasher + Clone,
+{
+  fn save<W: Write>(&self, writer: W, search_depth: u32) -> io::Result<()> {
+    let TableStorage::States(storage) = &self.storage else {
+      return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "tables keyed by Zobrist hashes do not store their states",
+      ));
+    };
+    write_table(writer, search_depth, storage.len() as u64, |write_entry| {
+      storage.try_for_each(write_entry)
+    })
+  }
+
+  fn load<R: Read>(&mut self, reader: R) -> io::Result<u32> {
+    read_table(reader, |game, score| self.update(game, score))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::hash_map::RandomState;
+
+  use abstract_game::Game;
+
+  use crate::{
+    evaluate::Evaluation,
+    test::{nim::Nim, tic_tac_toe::Ttt},
+    zobrist::ZobristHash,
+  };
+
+  use super::Table;
+
+  #[test]
+  fn test_evaluations_untracked() {
+    let table = Table::new();
+    table.update_evaluation(Nim::new(3), 2, Evaluation::heuristic(5));
+    assert_eq!(table.get_evaluation(&Nim::new(3)), None);
+  }
+
+  #[test]
+  fn test_evaluations_keep_deepest() {
+    let mut table = Table::new();
+    table.track_evaluations();
+    table.update_evaluation(Nim::new(3), 3, Evaluation::heuristic(5));
+    table.update_evaluation(Nim::new(3), 2, Evaluation::heuristic(6));
+    assert_eq!(
+      table.get_evaluation(&Nim::new(3)),
+      Some(Evaluation::heuristic(5))
+    );
+    table.update_evaluation(Nim::new(3), 3, Evaluation::heuristic(7));
+    assert_eq!(
+      table.get_evaluation(&Nim::new(3)),
+      Some(Evaluation::heuristic(7))
+    );
+  }
+
+  #[test]
+  fn test_evaluations_bounded() {
+    const CAPACITY: usize = 16;
+    let mut table = Table::bounded(CAPACITY, RandomState::new());
+    table.track_evaluations();
+    for sticks in 0..1000 {
+      table.update_evaluation(Nim::new(sticks), 1, Evaluation::heuristic(1));
+    }
+
+    let evaluated = (0..1000)
+      .filter(|&sticks| table.get_evaluation(&Nim::new(sticks)).is_some())
+      .count();
+    assert!((1..=CAPACITY).contains(&evaluated));
+  }
+
+  #[test]
+  fn test_evaluations_zobrist() {
+    let mut table = Table::zobrist(None, RandomState::new(), Ttt::zobrist_hash);
+    table.track_evaluations();
+    let ttt = Ttt::new().with_move(Ttt::new().each_move().next().unwrap());
+    table.update_evaluation(ttt.clone(), 4, Evaluation::heuristic(-3));
+    assert_eq!(table.get_evaluation(&ttt), Some(Evaluation::heuristic(-3)));
+    assert_eq!(table.get_evaluation(&Ttt::new()), None);
+  }
+}
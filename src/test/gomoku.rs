@@ -1,16 +1,28 @@
 use std::{
   fmt::{Debug, Display},
   hash::Hash,
+  io::{self, Read, Write},
 };
 
 use abstract_game::{Game, GameMoveIterator, GamePlayer, GameResult};
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord)]
+use crate::{
+  canonicalize::Canonicalize, evaluate::Evaluate, immediate_win::ImmediateWin,
+  move_order::MoveHeuristic, persist::EncodeGame,
+};
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct GomokuMove {
   x: u32,
   y: u32,
 }
 
+impl GomokuMove {
+  pub fn new(x: u32, y: u32) -> Self {
+    Self { x, y }
+  }
+}
+
 impl Display for GomokuMove {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "({}, {})", self.x, self.y)
@@ -110,6 +122,24 @@ impl Gomoku {
     debug_assert!(y < self.height);
     *self.tiles.get(self.idx(x, y)).unwrap()
   }
+
+  /// The number of `tile`s in a line with `m` in direction `(dx, dy)`, not
+  /// counting `m` itself.
+  fn line_through(&self, m: GomokuMove, dx: i32, dy: i32, tile: GomokuTile) -> u32 {
+    [(dx, dy), (-dx, -dy)]
+      .into_iter()
+      .map(|(dx, dy)| {
+        (1..)
+          .map(|i| (m.x as i32 + dx * i, m.y as i32 + dy * i))
+          .take_while(|&(x, y)| {
+            (0..self.width as i32).contains(&x)
+              && (0..self.height as i32).contains(&y)
+              && self.tile_at(x as u32, y as u32) == tile
+          })
+          .count() as u32
+      })
+      .sum()
+  }
 }
 
 impl Game for Gomoku {
@@ -123,7 +153,7 @@ impl Game for Gomoku {
   fn make_move(&mut self, m: Self::Move) {
     debug_assert_eq!(self.tile_at(m.x, m.y), GomokuTile::Empty);
     let idx = self.idx(m.x, m.y);
-    *self.tiles.get_mut(idx).unwrap() = if self.turn % 2 == 0 {
+    *self.tiles.get_mut(idx).unwrap() = if self.turn.is_multiple_of(2) {
       GomokuTile::X
     } else {
       GomokuTile::O
@@ -132,7 +162,7 @@ impl Game for Gomoku {
   }
 
   fn current_player(&self) -> GamePlayer {
-    if self.turn % 2 == 0 {
+    if self.turn.is_multiple_of(2) {
       GamePlayer::Player1
     } else {
       GamePlayer::Player2
@@ -193,6 +223,81 @@ impl Game for Gomoku {
   }
 }
 
+/// Every line of `to_win` tiles which only one player has pieces in counts for
+/// that player, and lines closer to being complete count for more.
+impl Evaluate for Gomoku {
+  fn evaluate(&self) -> i32 {
+    let player = match self.current_player() {
+      GamePlayer::Player1 => GomokuTile::X,
+      GamePlayer::Player2 => GomokuTile::O,
+    };
+    let to_win = self.to_win as i32;
+    let mut evaluation = 0;
+    for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+      for y in 0..self.height as i32 {
+        for x in 0..self.width as i32 {
+          let (end_x, end_y) = (x + dx * (to_win - 1), y + dy * (to_win - 1));
+          if !(0..self.width as i32).contains(&end_x) || !(0..self.height as i32).contains(&end_y) {
+            continue;
+          }
+          let tiles = (0..to_win).map(|i| self.tile_at((x + dx * i) as u32, (y + dy * i) as u32));
+          let (own, other) = tiles.fold((0, 0), |(own, other), tile| match tile {
+            GomokuTile::Empty => (own, other),
+            tile if tile == player => (own + 1, other),
+            _ => (own, other + 1),
+          });
+          if other == 0 {
+            evaluation += own * own;
+          } else if own == 0 {
+            evaluation -= other * other;
+          }
+        }
+      }
+    }
+    evaluation
+  }
+}
+
+impl ImmediateWin for Gomoku {
+  fn search_immediate_win(&self) -> Option<GomokuMove> {
+    let player = match self.current_player() {
+      GamePlayer::Player1 => GomokuTile::X,
+      GamePlayer::Player2 => GomokuTile::O,
+    };
+    self.each_move().find(|&m| {
+      [(1, 0), (0, 1), (1, 1), (1, -1)]
+        .into_iter()
+        .any(|(dx, dy)| self.line_through(m, dx, dy, player) + 1 >= self.to_win)
+    })
+  }
+}
+
+/// Moves which extend the longest lines of either player's pieces are the
+/// most likely to make or block a winning line.
+impl MoveHeuristic for Gomoku {
+  fn move_priority(&self, m: GomokuMove) -> i64 {
+    let (player, opponent) = match self.current_player() {
+      GamePlayer::Player1 => (GomokuTile::X, GomokuTile::O),
+      GamePlayer::Player2 => (GomokuTile::O, GomokuTile::X),
+    };
+    [(1, 0), (0, 1), (1, 1), (1, -1)]
+      .into_iter()
+      .map(|(dx, dy)| {
+        let own = self.line_through(m, dx, dy, player) as i64;
+        let other = self.line_through(m, dx, dy, opponent) as i64;
+        // Completing a line wins, and blocking one avoids losing.
+        if own + 1 >= self.to_win as i64 {
+          1_000
+        } else if other + 1 >= self.to_win as i64 {
+          100
+        } else {
+          own * own + other * other
+        }
+      })
+      .sum()
+  }
+}
+
 impl Hash for Gomoku {
   fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
     self.tiles.hash(state);
@@ -207,6 +312,106 @@ impl PartialEq for Gomoku {
 
 impl Eq for Gomoku {}
 
+impl Canonicalize for Gomoku {
+  /// Returns the reflection or rotation of the board whose tiles sort first.
+  /// Only square boards can be transposed.
+  fn canonicalize(&self) -> Self {
+    type Symmetry = fn(u32, u32, u32, u32) -> (u32, u32);
+    let symmetries: [Symmetry; 8] = [
+      |x, y, _, _| (x, y),
+      |x, y, w, _| (w - 1 - x, y),
+      |x, y, _, h| (x, h - 1 - y),
+      |x, y, w, h| (w - 1 - x, h - 1 - y),
+      |x, y, _, _| (y, x),
+      |x, y, _, h| (h - 1 - y, x),
+      |x, y, w, _| (y, w - 1 - x),
+      |x, y, w, h| (h - 1 - y, w - 1 - x),
+    ];
+    let count = if self.width == self.height { 8 } else { 4 };
+    let tiles = symmetries[..count]
+      .iter()
+      .map(|symmetry| {
+        let mut tiles = vec![GomokuTile::Empty; self.tiles.len()];
+        for y in 0..self.height {
+          for x in 0..self.width {
+            let (to_x, to_y) = symmetry(x, y, self.width, self.height);
+            tiles[self.idx(to_x, to_y)] = self.tile_at(x, y);
+          }
+        }
+        tiles
+      })
+      .min_by_key(|tiles| {
+        tiles
+          .iter()
+          .map(|tile| match tile {
+            GomokuTile::Empty => 0,
+            GomokuTile::X => 1,
+            GomokuTile::O => 2,
+          })
+          .collect::<Vec<u8>>()
+      })
+      .unwrap();
+    Self {
+      tiles,
+      ..self.clone()
+    }
+  }
+}
+
+impl EncodeGame for Gomoku {
+  const GAME_TYPE: &'static str = "gomoku";
+
+  fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    for dimension in [self.width, self.height, self.to_win] {
+      writer.write_all(&dimension.to_le_bytes())?;
+    }
+    let tiles: Vec<u8> = self
+      .tiles
+      .iter()
+      .map(|tile| match tile {
+        GomokuTile::Empty => 0,
+        GomokuTile::X => 1,
+        GomokuTile::O => 2,
+      })
+      .collect();
+    writer.write_all(&tiles)
+  }
+
+  fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+    let mut dimensions = [0; 12];
+    reader.read_exact(&mut dimensions)?;
+    let [width, height, to_win] =
+      [0, 4, 8].map(|i| u32::from_le_bytes(dimensions[i..i + 4].try_into().unwrap()));
+
+    let mut tiles = vec![0; (width * height) as usize];
+    reader.read_exact(&mut tiles)?;
+    let tiles = tiles
+      .into_iter()
+      .map(|tile| match tile {
+        0 => Ok(GomokuTile::Empty),
+        1 => Ok(GomokuTile::X),
+        2 => Ok(GomokuTile::O),
+        _ => Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("invalid gomoku tile {tile}"),
+        )),
+      })
+      .collect::<io::Result<Vec<_>>>()?;
+    let turn = tiles
+      .iter()
+      .filter(|&&tile| tile != GomokuTile::Empty)
+      .count() as u32;
+
+    Ok(Self {
+      tiles,
+      width,
+      height,
+      to_win,
+      turn,
+    })
+  }
+}
+
 impl Display for Gomoku {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     for y in 0..self.height {
@@ -1,10 +1,13 @@
 use std::{
   fmt::{Debug, Display},
   hash::Hash,
+  io::{self, Read, Write},
 };
 
 use abstract_game::{Game, GameMoveIterator, GamePlayer, GameResult, Score};
 
+use crate::persist::EncodeGame;
+
 #[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub struct NimMove {
   sticks: u32,
@@ -48,7 +51,7 @@ impl Nim {
   }
 
   pub fn expected_score(&self) -> Score {
-    if self.sticks % 3 == 0 {
+    if self.sticks.is_multiple_of(3) {
       let turn_count_win = self.sticks * 2 / 3;
       Score::optimal_lose(turn_count_win)
     } else {
@@ -75,7 +78,7 @@ impl Game for Nim {
   }
 
   fn current_player(&self) -> GamePlayer {
-    if self.turn % 2 == 0 {
+    if self.turn.is_multiple_of(2) {
       GamePlayer::Player1
     } else {
       GamePlayer::Player2
@@ -85,7 +88,7 @@ impl Game for Nim {
   fn finished(&self) -> GameResult {
     if self.sticks == 0 {
       // The winner is the player to take the last stick.
-      if self.turn % 2 == 0 {
+      if self.turn.is_multiple_of(2) {
         GameResult::Win(GamePlayer::Player2)
       } else {
         GameResult::Win(GamePlayer::Player1)
@@ -110,6 +113,26 @@ impl PartialEq for Nim {
 
 impl Eq for Nim {}
 
+impl EncodeGame for Nim {
+  const GAME_TYPE: &'static str = "nim";
+
+  fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&self.sticks.to_le_bytes())?;
+    writer.write_all(&self.turn.to_le_bytes())
+  }
+
+  fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+    let mut sticks = [0; 4];
+    reader.read_exact(&mut sticks)?;
+    let mut turn = [0; 4];
+    reader.read_exact(&mut turn)?;
+    Ok(Self {
+      sticks: u32::from_le_bytes(sticks),
+      turn: u32::from_le_bytes(turn),
+    })
+  }
+}
+
 impl Display for Nim {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "{} (turn {})", self.sticks, self.turn)
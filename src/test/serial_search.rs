@@ -76,15 +76,15 @@ where
       None => Score::win(2),
     };
 
-    match best_score.clone() {
+    match best_score {
       Some(best_score_val) => {
         if score.better(best_score_val) {
-          best_score = Some(score.clone());
+          best_score = Some(score);
           best_move = Some(m);
         }
       }
       None => {
-        best_score = Some(score.clone());
+        best_score = Some(score);
         best_move = Some(m);
       }
     }
@@ -97,7 +97,7 @@ where
   }
 
   if let Some(ref score) = best_score {
-    check_score(game.clone(), score.clone(), table);
+    check_score(game.clone(), *score, table);
   }
   (best_score, best_move)
 }
@@ -1,11 +1,17 @@
 use std::{
   fmt::{Debug, Display},
   hash::Hash,
+  io::{self, Read, Write},
 };
 
 use abstract_game::{Game, GameMoveIterator, GamePlayer, GameResult, Score};
 
-use crate::test::serial_search::find_best_move_serial;
+use crate::{
+  canonicalize::Canonicalize,
+  persist::EncodeGame,
+  test::serial_search::find_best_move_serial,
+  zobrist::{ZobristHash, ZobristKeys},
+};
 
 #[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub struct TttMove {
@@ -125,7 +131,7 @@ impl Game for Ttt {
 
   fn make_move(&mut self, m: Self::Move) {
     let mut mask = 1u32 << Self::idx(m.x, m.y);
-    if self.turn % 2 != 0 {
+    if !self.turn.is_multiple_of(2) {
       mask <<= 16;
     }
     self.tile_mask |= mask;
@@ -133,7 +139,7 @@ impl Game for Ttt {
   }
 
   fn current_player(&self) -> GamePlayer {
-    if self.turn % 2 == 0 {
+    if self.turn.is_multiple_of(2) {
       GamePlayer::Player1
     } else {
       GamePlayer::Player2
@@ -177,6 +183,75 @@ impl PartialEq for Ttt {
 
 impl Eq for Ttt {}
 
+impl Canonicalize for Ttt {
+  /// Returns the symmetry of the board with the smallest tile mask.
+  fn canonicalize(&self) -> Self {
+    type Symmetry = fn(u32, u32) -> (u32, u32);
+    let symmetries: [Symmetry; 8] = [
+      |x, y| (x, y),
+      |x, y| (2 - x, y),
+      |x, y| (x, 2 - y),
+      |x, y| (2 - x, 2 - y),
+      |x, y| (y, x),
+      |x, y| (2 - y, x),
+      |x, y| (y, 2 - x),
+      |x, y| (2 - y, 2 - x),
+    ];
+    let tile_mask = symmetries
+      .into_iter()
+      .map(|symmetry| {
+        (0..3)
+          .flat_map(|y| (0..3).map(move |x| (x, y)))
+          .fold(0, |mask, (x, y)| {
+            let (to_x, to_y) = symmetry(x, y);
+            let tile = (self.tile_mask >> Self::idx(x, y)) & 0x0001_0001;
+            mask | (tile << Self::idx(to_x, to_y))
+          })
+      })
+      .min()
+      .unwrap();
+    Self {
+      tile_mask,
+      turn: self.turn,
+    }
+  }
+}
+
+impl ZobristHash for Ttt {
+  fn zobrist_hash(&self) -> u64 {
+    static KEYS: ZobristKeys<9, 2> = ZobristKeys::new(0x7777);
+    let pieces = (0..3)
+      .flat_map(|y| (0..3).map(move |x| (x, y)))
+      .filter_map(|(x, y)| {
+        let square = (x + 3 * y) as usize;
+        match self.tile_at(x, y) {
+          TttTile::Empty => None,
+          TttTile::X => Some((square, 0)),
+          TttTile::O => Some((square, 1)),
+        }
+      });
+    KEYS.hash(pieces, self.current_player() == GamePlayer::Player2)
+  }
+}
+
+impl EncodeGame for Ttt {
+  const GAME_TYPE: &'static str = "tic-tac-toe";
+
+  fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&self.tile_mask.to_le_bytes())
+  }
+
+  fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    let tile_mask = u32::from_le_bytes(bytes);
+    Ok(Self {
+      tile_mask,
+      turn: tile_mask.count_ones(),
+    })
+  }
+}
+
 impl Display for Ttt {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     for y in 0..3 {
@@ -0,0 +1,149 @@
+/// Games which can compute a 64-bit Zobrist hash of their state. Distinct
+/// states should have distinct hashes with overwhelming probability, since
+/// tables keyed by these hashes treat states which share a hash as the same
+/// state.
+///
+/// Games which implement this can also implement `Hash` by writing only the
+/// Zobrist hash, with `state.write_u64(self.zobrist_hash())`, and use
+/// `BuildPassThroughHasher` as the hasher of the cooperative solver.
+pub trait ZobristHash {
+  fn zobrist_hash(&self) -> u64;
+}
+
+/// Random keys for building Zobrist hashes of a board with `SQUARES` squares,
+/// each of which may hold one of `PIECES` kinds of pieces. The hash of a state
+/// is the xor of the key of every piece on the board, xored with `turn_key` if
+/// it is the second player's turn. Since xor is its own inverse, games can
+/// keep the hash up to date as moves are made rather than recomputing it:
+///
+/// ```
+/// use cooperate::zobrist::ZobristKeys;
+///
+/// static KEYS: ZobristKeys<9, 2> = ZobristKeys::new(0x5eed);
+///
+/// let mut hash = 0;
+/// // Player 1 places piece 0 on square 4, then player 2 places piece 1 on
+/// // square 0.
+/// hash ^= KEYS.piece_key(4, 0) ^ KEYS.turn_key();
+/// hash ^= KEYS.piece_key(0, 1) ^ KEYS.turn_key();
+/// assert_eq!(hash, KEYS.hash([(4, 0), (0, 1)], false));
+/// ```
+pub struct ZobristKeys<const SQUARES: usize, const PIECES: usize> {
+  pieces: [[u64; PIECES]; SQUARES],
+  turn: u64,
+}
+
+impl<const SQUARES: usize, const PIECES: usize> ZobristKeys<SQUARES, PIECES> {
+  /// Generates the keys from `seed`. Keys generated from the same seed are the
+  /// same in every process, so hashes built from them may be saved.
+  pub const fn new(seed: u64) -> Self {
+    let mut state = seed;
+    let mut pieces = [[0; PIECES]; SQUARES];
+    let mut square = 0;
+    while square < SQUARES {
+      let mut piece = 0;
+      while piece < PIECES {
+        pieces[square][piece] = split_mix64(&mut state);
+        piece += 1;
+      }
+      square += 1;
+    }
+    let turn = split_mix64(&mut state);
+    Self { pieces, turn }
+  }
+
+  /// The key of `piece` being on `square`.
+  pub const fn piece_key(&self, square: usize, piece: usize) -> u64 {
+    self.pieces[square][piece]
+  }
+
+  /// The key which is toggled every time the player to move changes.
+  pub const fn turn_key(&self) -> u64 {
+    self.turn
+  }
+
+  /// Computes the hash of a board with each `(square, piece)` in `pieces`.
+  pub fn hash(
+    &self,
+    pieces: impl IntoIterator<Item = (usize, usize)>,
+    second_player_to_move: bool,
+  ) -> u64 {
+    let turn = if second_player_to_move { self.turn } else { 0 };
+    pieces.into_iter().fold(turn, |hash, (square, piece)| {
+      hash ^ self.piece_key(square, piece)
+    })
+  }
+}
+
+/// The SplitMix64 generator, which is used so the keys don't depend on the
+/// version of any random number generation library.
+const fn split_mix64(state: &mut u64) -> u64 {
+  *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+  let mut z = *state;
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+  z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+
+  use abstract_game::Solver;
+
+  use crate::{
+    solvers::ttable_solver::TTSolver,
+    test::tic_tac_toe::Ttt,
+    zobrist::{ZobristHash, ZobristKeys},
+  };
+
+  #[test]
+  fn test_keys_distinct() {
+    static KEYS: ZobristKeys<64, 12> = ZobristKeys::new(1);
+    let keys: HashSet<_> = (0..64)
+      .flat_map(|square| (0..12).map(move |piece| KEYS.piece_key(square, piece)))
+      .chain([KEYS.turn_key()])
+      .collect();
+    assert_eq!(keys.len(), 64 * 12 + 1);
+  }
+
+  #[test]
+  fn test_ttt_hashes_distinct() {
+    let mut solver = TTSolver::new();
+    solver.best_move(&Ttt::new(), 9);
+    let hashes: HashSet<_> = solver
+      .table()
+      .keys()
+      .map(|ttt| ttt.zobrist_hash())
+      .collect();
+    assert_eq!(hashes.len(), solver.table().len());
+  }
+
+  #[test]
+  fn test_keys_deterministic() {
+    let keys1 = ZobristKeys::<4, 2>::new(7);
+    let keys2 = ZobristKeys::<4, 2>::new(7);
+    let keys3 = ZobristKeys::<4, 2>::new(8);
+    assert_eq!(
+      keys1.hash([(0, 1), (3, 0)], true),
+      keys2.hash([(0, 1), (3, 0)], true)
+    );
+    assert_ne!(
+      keys1.hash([(0, 1), (3, 0)], true),
+      keys3.hash([(0, 1), (3, 0)], true)
+    );
+  }
+
+  #[test]
+  fn test_hash_order_independent() {
+    let keys = ZobristKeys::<9, 2>::new(3);
+    assert_eq!(
+      keys.hash([(0, 0), (4, 1), (8, 0)], true),
+      keys.hash([(8, 0), (0, 0), (4, 1)], true)
+    );
+    assert_ne!(
+      keys.hash([(0, 0), (4, 1), (8, 0)], true),
+      keys.hash([(0, 0), (4, 1), (8, 0)], false)
+    );
+  }
+}